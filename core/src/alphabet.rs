@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+/// A totally ordered input symbol whose neighbours can be computed, so that
+/// inclusive ranges of symbols can be split and complemented.
+pub trait Letter: Copy + Ord + Hash + Debug {
+    const MIN: Self;
+    const MAX: Self;
+    fn succ(self) -> Option<Self>;
+    fn pred(self) -> Option<Self>;
+}
+
+impl Letter for char {
+    const MIN: Self = '\0';
+    const MAX: Self = char::MAX;
+    fn succ(self) -> Option<Self> {
+        match self {
+            // skip the surrogate gap
+            '\u{D7FF}' => Some('\u{E000}'),
+            _ => std::char::from_u32(self as u32 + 1),
+        }
+    }
+    fn pred(self) -> Option<Self> {
+        match self {
+            '\0' => None,
+            '\u{E000}' => Some('\u{D7FF}'),
+            _ => std::char::from_u32(self as u32 - 1),
+        }
+    }
+}
+
+macro_rules! impl_letter {
+    ($($t:ty),*) => {
+        $(
+            impl Letter for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+                fn succ(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+                fn pred(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+            }
+        )*
+    };
+}
+
+impl_letter!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+/// A set of symbols, stored as sorted disjoint inclusive ranges.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Alphabet<I> {
+    ranges: Vec<(I, I)>,
+}
+
+impl<I: Letter> Alphabet<I> {
+    pub fn new(ranges: impl IntoIterator<Item = (I, I)>) -> Self {
+        Self {
+            ranges: normalize(ranges),
+        }
+    }
+
+    /// Every value of the symbol type.
+    pub fn full() -> Self {
+        Self::new(vec![(I::MIN, I::MAX)])
+    }
+
+    pub fn ranges(&self) -> &[(I, I)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, symbol: I) -> bool {
+        let range = self.ranges.partition_point(|range| range.1 < symbol);
+        self.ranges
+            .get(range)
+            .is_some_and(|range| range.0 <= symbol)
+    }
+
+    pub fn union(&self, other: &Alphabet<I>) -> Self {
+        Self::new(self.ranges.iter().chain(other.ranges.iter()).cloned())
+    }
+}
+
+impl<I: Letter> FromIterator<I> for Alphabet<I> {
+    fn from_iter<T: IntoIterator<Item = I>>(symbols: T) -> Self {
+        Self::new(symbols.into_iter().map(|symbol| (symbol, symbol)))
+    }
+}
+
+/// Split the symbols covered by `ranges` into the fewest disjoint inclusive
+/// ranges such that every input range is a union of some of them.
+pub fn partition<I: Letter>(ranges: impl IntoIterator<Item = (I, I)>) -> Vec<(I, I)> {
+    // cut point => change in the number of ranges covering it
+    let mut cuts = BTreeMap::<I, i32>::new();
+    for (lo, hi) in ranges {
+        *cuts.entry(lo).or_default() += 1;
+        if let Some(end) = hi.succ() {
+            *cuts.entry(end).or_default() -= 1;
+        }
+    }
+    let cuts = cuts.into_iter().collect::<Vec<_>>();
+    let mut depth = 0;
+    let mut result = vec![];
+    for (i, &(cut, delta)) in cuts.iter().enumerate() {
+        depth += delta;
+        if depth > 0 {
+            let end = match cuts.get(i + 1) {
+                Some(&(next, _)) => next.pred().unwrap(),
+                None => I::MAX,
+            };
+            result.push((cut, end));
+        }
+    }
+    result
+}
+
+/// Merge overlapping and adjacent ranges into a sorted list of disjoint ones.
+pub fn normalize<I: Letter>(ranges: impl IntoIterator<Item = (I, I)>) -> Vec<(I, I)> {
+    let mut ranges = ranges.into_iter().collect::<Vec<_>>();
+    ranges.sort();
+    let mut result: Vec<(I, I)> = vec![];
+    for (lo, hi) in ranges {
+        match result.last_mut() {
+            Some(last) if last.1.succ().is_none_or(|next| next >= lo) => {
+                last.1 = last.1.max(hi);
+            }
+            _ => result.push((lo, hi)),
+        }
+    }
+    result
+}
+
+/// Every symbol not covered by `ranges`, as sorted disjoint ranges.
+pub fn complement<I: Letter>(ranges: impl IntoIterator<Item = (I, I)>) -> Vec<(I, I)> {
+    let mut result = vec![];
+    let mut next = Some(I::MIN);
+    for (lo, hi) in normalize(ranges) {
+        if let Some(start) = next {
+            if start < lo {
+                result.push((start, lo.pred().unwrap()));
+            }
+        }
+        next = hi.succ();
+    }
+    if let Some(start) = next {
+        result.push((start, I::MAX));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_partition() {
+        assert_eq!(
+            vec![('a', 'b'), ('c', 'c'), ('d', 'z'), ('~', '~')],
+            partition(vec![('a', 'z'), ('c', 'c'), ('a', 'c'), ('~', '~')])
+        );
+        assert_eq!(
+            vec![(0u8, 9), (10, 255)],
+            partition(vec![(0u8, 255), (0, 9)])
+        );
+        assert!(partition(Vec::<(char, char)>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphabet = "abcxz".chars().collect::<Alphabet<_>>();
+        assert_eq!(&[('a', 'c'), ('x', 'x'), ('z', 'z')], alphabet.ranges());
+        assert!(alphabet.contains('b') && alphabet.contains('z'));
+        assert!(!alphabet.contains('y') && !alphabet.contains('`'));
+
+        let alphabet = alphabet.union(&Alphabet::new(vec![('d', 'w')]));
+        assert_eq!(&[('a', 'x'), ('z', 'z')], alphabet.ranges());
+        assert!(Alphabet::<u8>::full().contains(255));
+        assert!(Alphabet::<u8>::new(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_and_complement() {
+        assert_eq!(
+            vec![('a', 'f'), ('x', 'z')],
+            normalize(vec![('x', 'z'), ('d', 'f'), ('a', 'c'), ('b', 'e')])
+        );
+        assert_eq!(
+            vec![(0u8, 96), (100, 119), (123, 255)],
+            complement(vec![(b'x', b'z'), (b'a', b'c')])
+        );
+        assert_eq!(vec![(0u8, 255)], complement(vec![]));
+        assert!(complement(vec![(0u8, 255)]).is_empty());
+        assert_eq!(Some('\u{E000}'), '\u{D7FF}'.succ());
+        assert_eq!(None, char::MAX.succ());
+    }
+}
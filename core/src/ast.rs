@@ -0,0 +1,103 @@
+use std::fmt::{Display, Write};
+use std::rc::Rc;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ast<T> {
+    token: T,
+    children: Option<Vec<Rc<Ast<T>>>>,
+}
+
+impl<T> Ast<T> {
+    pub fn new(token: T, children: Option<Vec<Rc<Ast<T>>>>) -> Self {
+        Ast { token, children }
+    }
+
+    pub fn token(&self) -> &T {
+        &self.token
+    }
+
+    pub fn children(&self) -> Option<&Vec<Rc<Ast<T>>>> {
+        self.children.as_ref()
+    }
+}
+
+impl<T: Display> Ast<T> {
+    /// Draw the tree one token per line, each child below its parent and
+    /// joined to it by a branch.
+    pub fn to_tree(&self) -> String {
+        let mut tree = String::new();
+        self.write_tree(&mut tree, "", "");
+        tree
+    }
+
+    /// `first` starts the line of this node, `rest` the lines below it.
+    fn write_tree(&self, tree: &mut String, first: &str, rest: &str) {
+        writeln!(tree, "{}{}", first, self.token).unwrap();
+        let children = self.children.iter().flatten().collect::<Vec<_>>();
+        for (i, child) in children.iter().enumerate() {
+            let (branch, indent) = if i + 1 == children.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            child.write_tree(
+                tree,
+                &(rest.to_string() + branch),
+                &(rest.to_string() + indent),
+            );
+        }
+    }
+
+    /// The tree as an S-expression: a leaf is its token and a node with
+    /// children is `(token child...)`.
+    pub fn to_sexpr(&self) -> String {
+        match &self.children {
+            Some(children) => {
+                let mut sexpr = format!("({}", self.token);
+                for child in children {
+                    write!(sexpr, " {}", child.to_sexpr()).unwrap();
+                }
+                sexpr + ")"
+            }
+            None => self.token.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::re::Re;
+    #[test]
+    fn test_ast_printers() {
+        let ast = Re::new("(?<x>a|[^b-d])*b{2,}|\\0(?:)").ast().clone();
+        assert_eq!(
+            "(alter (concat (* (group 1 <x> (alter a [^b-d]))) ({2,} b)) (concat ∅ ε))",
+            ast.to_sexpr()
+        );
+        assert_eq!(
+            "\
+alter
+├── concat
+│   ├── *
+│   │   └── group 1 <x>
+│   │       └── alter
+│   │           ├── a
+│   │           └── [^b-d]
+│   └── {2,}
+│       └── b
+└── concat
+    ├── ∅
+    └── ε
+",
+            ast.to_tree()
+        );
+
+        let leaf = |token| Rc::new(Ast::new(token, None));
+        let ast = Ast::new(1, Some(vec![leaf(2), leaf(3)]));
+        assert_eq!("(1 2 3)", ast.to_sexpr());
+        assert_eq!("1\n├── 2\n└── 3\n", ast.to_tree());
+        assert_eq!("(1)", Ast::new(1, Some(vec![])).to_sexpr());
+        assert_eq!("1\n", Ast::<i32>::new(1, None).to_tree());
+    }
+}
@@ -0,0 +1,107 @@
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub struct Trace<S> {
+    accept: bool,
+    trace: Vec<S>,
+}
+
+impl<S> Trace<S> {
+    pub fn new(accept: bool, trace: Vec<S>) -> Self {
+        Self { accept, trace }
+    }
+    pub fn push(&mut self, state: S) {
+        self.trace.push(state);
+    }
+    pub fn accept(&self) -> bool {
+        self.accept
+    }
+    pub fn trace(&self) -> &[S] {
+        &self.trace
+    }
+}
+
+/// A match of an automaton inside a larger input, spanning the symbols
+/// `start..end`. For `char` input these are char offsets, for `u8` input
+/// byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Match {
+    start: usize,
+    end: usize,
+}
+
+impl Match {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Successive non-overlapping matches, produced by a function finding the
+/// first match at or after an offset. After an empty match the search
+/// resumes one symbol later.
+pub struct Matches<F> {
+    find_at: F,
+    at: usize,
+    len: usize,
+}
+
+impl<F> Matches<F>
+where
+    F: FnMut(usize) -> Option<Match>,
+{
+    pub fn new(len: usize, find_at: F) -> Self {
+        Self {
+            find_at,
+            at: 0,
+            len,
+        }
+    }
+}
+
+impl<F> Iterator for Matches<F>
+where
+    F: FnMut(usize) -> Option<Match>,
+{
+    type Item = Match;
+    fn next(&mut self) -> Option<Match> {
+        if self.at > self.len {
+            return None;
+        }
+        let found = (self.find_at)(self.at);
+        match found {
+            Some(found) if found.is_empty() => self.at = found.end() + 1,
+            Some(found) => self.at = found.end(),
+            None => self.at = self.len + 1,
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_matches() {
+        let found = vec![Match::new(1, 3), Match::new(3, 3), Match::new(5, 6)];
+        let find_at = |at| found.iter().cloned().find(|found| found.start() >= at);
+        let matches = Matches::new(6, find_at).collect::<Vec<_>>();
+        assert_eq!(found, matches);
+        assert_eq!(2, Match::new(1, 3).len());
+        assert_eq!(1..3, Match::new(1, 3).range());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::alphabet;
+
+/// A bracketed character class such as `[a-z_]` or `[^0-9]`, or the `.`
+/// wildcard, which is the negation of the empty class.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CharClass {
+    negated: bool,
+    items: Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// `items` are inclusive ranges in the order they were written; a single
+    /// character `c` is the range `(c, c)`.
+    pub fn new(negated: bool, items: Vec<(char, char)>) -> Self {
+        Self { negated, items }
+    }
+
+    /// The class matching any character, written `.`.
+    pub fn any() -> Self {
+        Self::new(true, vec![])
+    }
+
+    pub fn negated(&self) -> bool {
+        self.negated
+    }
+
+    pub fn items(&self) -> &[(char, char)] {
+        &self.items
+    }
+
+    /// The matched characters as sorted, disjoint inclusive ranges.
+    pub fn ranges(&self) -> Vec<(char, char)> {
+        if self.negated {
+            alphabet::complement(self.items.iter().cloned())
+        } else {
+            alphabet::normalize(self.items.iter().cloned())
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        let contained = self.items.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+        contained != self.negated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_char_class() {
+        let class = CharClass::new(false, vec![('a', 'z'), ('0', '9'), ('_', '_'), ('x', 'x')]);
+        assert_eq!(vec![('0', '9'), ('_', '_'), ('a', 'z')], class.ranges());
+        assert!(class.contains('q') && class.contains('_') && !class.contains('-'));
+
+        let class = CharClass::new(true, vec![('b', 'y')]);
+        assert_eq!(vec![('\0', 'a'), ('z', char::MAX)], class.ranges());
+        assert!(class.contains('a') && !class.contains('c'));
+
+        assert_eq!(vec![('\0', char::MAX)], CharClass::any().ranges());
+    }
+}
@@ -0,0 +1,1181 @@
+use crate::alphabet::{self, Alphabet, Letter};
+use crate::automatan::{Match, Matches, Trace};
+use crate::dot;
+use crate::vm::Vm;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// An edge label: every symbol in an inclusive range.
+#[derive(Hash, PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Transition<I>(I, I);
+
+impl<I> Transition<I> {
+    pub fn range(lo: I, hi: I) -> Self {
+        Transition(lo, hi)
+    }
+    pub fn start(&self) -> &I {
+        &self.0
+    }
+    pub fn end(&self) -> &I {
+        &self.1
+    }
+}
+
+impl<I: Clone> Transition<I> {
+    pub fn new(c: I) -> Self {
+        Transition(c.clone(), c)
+    }
+}
+
+impl<I: Ord> Transition<I> {
+    pub fn contains(&self, symbol: &I) -> bool {
+        self.0 <= *symbol && *symbol <= self.1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SetState<S: Hash + Eq>(HashSet<S>);
+
+impl<S: Hash + Eq> SetState<S> {
+    pub fn new(set: HashSet<S>) -> Self {
+        SetState(set)
+    }
+}
+
+impl<S: Hash + Eq + PartialOrd> PartialEq for SetState<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_subset(&other.0) && other.0.is_subset(&self.0)
+    }
+}
+
+impl<S: Hash + Eq + PartialOrd> Eq for SetState<S> {}
+
+impl<S: Hash + Eq + Ord> SetState<S> {
+    fn sorted(&self) -> Vec<&S> {
+        let mut states = self.0.iter().collect::<Vec<_>>();
+        states.sort();
+        states
+    }
+}
+
+impl<S: Hash + Eq + Ord> PartialOrd for SetState<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: Hash + Eq + Ord> Ord for SetState<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted().cmp(&other.sorted())
+    }
+}
+
+impl<S: Hash + Eq + Ord + fmt::Display> fmt::Display for SetState<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let states = self
+            .sorted()
+            .iter()
+            .map(|state| state.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{{{}}}", states.join(", "))
+    }
+}
+
+impl<S: Hash + Eq + Ord> Hash for SetState<S> {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        for s in self.sorted() {
+            s.hash(state);
+        }
+    }
+}
+
+/// The edges leaving each state, sorted by symbol.
+type Index<S, I> = HashMap<S, Vec<(I, I, S)>>;
+
+#[derive(Clone)]
+pub struct Dfa<S, I>
+where
+    S: Hash + Eq,
+    I: Hash + Eq,
+{
+    start: S,
+    accept_states: HashSet<S>,
+    transitions: HashMap<(S, Transition<I>), S>,
+    tags: HashMap<S, usize>,
+    // built on first use, and dropped whenever the transitions change
+    index: OnceLock<Index<S, I>>,
+}
+
+impl<S, I> PartialEq for Dfa<S, I>
+where
+    S: Hash + Eq,
+    I: Hash + Eq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+            && self.accept_states == other.accept_states
+            && self.transitions == other.transitions
+            && self.tags == other.tags
+    }
+}
+
+impl<S, I> Debug for Dfa<S, I>
+where
+    S: Hash + Eq + Debug,
+    I: Hash + Eq + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dfa")
+            .field("start", &self.start)
+            .field("accept_states", &self.accept_states)
+            .field("transitions", &self.transitions)
+            .field("tags", &self.tags)
+            .finish()
+    }
+}
+
+impl<S, I> Dfa<S, I>
+where
+    S: Hash + Eq,
+    I: Hash + Eq,
+{
+    pub fn new(
+        start: S,
+        accept_states: HashSet<S>,
+        transitions: HashMap<(S, Transition<I>), S>,
+    ) -> Self {
+        Self {
+            start,
+            accept_states,
+            transitions,
+            tags: HashMap::new(),
+            index: OnceLock::new(),
+        }
+    }
+
+    /// Tag accept states with the index of the rule they accept for.
+    pub fn with_tags(mut self, tags: HashMap<S, usize>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn add_transition(&mut self, transition: ((S, Transition<I>), S)) {
+        self.transitions.insert(transition.0, transition.1);
+        self.index.take();
+    }
+
+    pub fn start(&self) -> &S {
+        &self.start
+    }
+
+    pub fn accept_states(&self) -> &HashSet<S> {
+        &self.accept_states
+    }
+
+    pub fn transitions(&self) -> &HashMap<(S, Transition<I>), S> {
+        &self.transitions
+    }
+
+    /// Rule indices of tagged accept states, see `Nfa::tags`.
+    pub fn tags(&self) -> &HashMap<S, usize> {
+        &self.tags
+    }
+}
+
+impl<S, I> Dfa<S, I>
+where
+    S: Hash + Eq + Clone,
+    I: Hash + Eq + Ord + Clone,
+{
+    /// The state reached from `state` by consuming `symbol`, if any. The
+    /// edges leaving `state` are searched by bisection.
+    pub fn next(&self, state: &S, symbol: &I) -> Option<&S> {
+        let index = self.index.get_or_init(|| {
+            let mut index = Index::<S, I>::new();
+            for ((from, input), to) in self.transitions.iter() {
+                let edge = (input.0.clone(), input.1.clone(), to.clone());
+                index.entry(from.clone()).or_default().push(edge);
+            }
+            for edges in index.values_mut() {
+                edges.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            index
+        });
+        let edges = index.get(state)?;
+        let edge = edges.partition_point(|(_, hi, _)| hi < symbol);
+        edges
+            .get(edge)
+            .filter(|(lo, _, _)| lo <= symbol)
+            .map(|(_, _, to)| to)
+    }
+
+    pub fn run(&self, input: &[I]) -> Trace<S> {
+        let mut state = self.start.clone();
+        let mut trace = vec![];
+        for symbol in input {
+            trace.push(state.clone());
+            let next = self.next(&state, symbol).cloned();
+            if next.is_none() {
+                return Trace::new(false, trace);
+            }
+            state = next.unwrap();
+        }
+        let accept = self.accept_states.contains(&state);
+        trace.push(state);
+        Trace::new(accept, trace)
+    }
+
+    /// End of the longest match starting exactly at `start`, walking the
+    /// automaton like `run` until it gets stuck.
+    pub fn longest_match_at(&self, input: &[I], start: usize) -> Option<usize> {
+        let mut state = &self.start;
+        let mut end = None;
+        for at in start..=input.len() {
+            if self.accept_states.contains(state) {
+                end = Some(at);
+            }
+            match input.get(at).and_then(|symbol| self.next(state, symbol)) {
+                Some(next) => state = next,
+                None => break,
+            }
+        }
+        end
+    }
+}
+
+/// A dense view of the part of a `Dfa` reachable from its start state.
+/// States are numbered in BFS order with the start state as 0, and the
+/// symbols on the edges are split into disjoint classes.
+struct Table<S, I> {
+    states: Vec<S>,
+    classes: Vec<(I, I)>,
+    next: Vec<Vec<Option<usize>>>,
+}
+
+/// Append an edge to `edges`, which are sorted by symbol. Adjacent ranges
+/// leading to the same target share one edge.
+pub(crate) fn push_edge<I: Letter, T: PartialEq>(
+    edges: &mut Vec<(I, I, T)>,
+    lo: I,
+    hi: I,
+    target: T,
+) {
+    match edges.last_mut() {
+        Some(last) if last.2 == target && last.1.succ() == Some(lo) => last.1 = hi,
+        _ => edges.push((lo, hi, target)),
+    }
+}
+
+impl<S, I: Letter> Table<S, I> {
+    /// The class containing `symbol`, if any.
+    fn class(&self, symbol: I) -> Option<usize> {
+        let class = self.classes.partition_point(|class| class.1 < symbol);
+        match self.classes.get(class) {
+            Some(&(lo, _)) if lo <= symbol => Some(class),
+            _ => None,
+        }
+    }
+
+    /// The state reached from `state` on `symbol`, `None` meaning dead.
+    fn step(&self, state: usize, symbol: I) -> Option<usize> {
+        self.next[state][self.class(symbol)?]
+    }
+}
+
+impl<S, I> Dfa<S, I>
+where
+    S: Hash + Eq + Clone,
+    I: Letter,
+{
+    fn table(&self) -> Table<S, I> {
+        let classes =
+            alphabet::partition(self.transitions.keys().map(|(_, input)| (input.0, input.1)));
+        let mut edges = HashMap::<&S, Vec<(&Transition<I>, &S)>>::new();
+        for ((state, input), target) in self.transitions.iter() {
+            edges.entry(state).or_default().push((input, target));
+        }
+
+        let mut index = HashMap::new();
+        index.insert(&self.start, 0);
+        let mut states = vec![&self.start];
+        let mut next = vec![];
+        while next.len() < states.len() {
+            let mut row = vec![None; classes.len()];
+            let mut edges = edges.get(states[next.len()]).cloned().unwrap_or_default();
+            edges.sort_by_key(|(input, _)| input.0);
+            for (input, target) in edges {
+                let id = *index.entry(target).or_insert_with(|| {
+                    states.push(target);
+                    states.len() - 1
+                });
+                let first = classes.partition_point(|class| class.0 < input.0);
+                for class in first..classes.len() {
+                    if classes[class].0 > input.1 {
+                        break;
+                    }
+                    row[class] = Some(id);
+                }
+            }
+            next.push(row);
+        }
+        Table {
+            states: states.into_iter().cloned().collect(),
+            classes,
+            next,
+        }
+    }
+
+    /// End of the first match anywhere in `input`, i.e. the smallest offset
+    /// at which some match ends. The automaton runs from every offset at
+    /// once, tracking the set of states it can be in, so this takes time
+    /// linear in the length of `input`.
+    pub fn shortest_match(&self, input: &[I]) -> Option<usize> {
+        let table = self.table();
+        let accepting = self.accepting(&table);
+        let mut live = vec![false; table.states.len()];
+        for at in 0..=input.len() {
+            // a match may start here
+            live[0] = true;
+            if (0..live.len()).any(|state| live[state] && accepting[state]) {
+                return Some(at);
+            }
+            let mut after = vec![false; live.len()];
+            if let Some(&symbol) = input.get(at) {
+                let states = (0..live.len()).filter(|&state| live[state]);
+                for next in states.filter_map(|state| table.step(state, symbol)) {
+                    after[next] = true;
+                }
+            }
+            live = after;
+        }
+        None
+    }
+
+    /// The leftmost-longest match in `input`, see `Dfa::find_at`.
+    pub fn find(&self, input: &[I]) -> Option<Match> {
+        self.find_at(input, 0)
+    }
+
+    /// The leftmost-longest match starting at or after `from`: the match
+    /// with the smallest start, and among those the one ending last. The
+    /// start is found by one backward pass over `input`, see
+    /// `Dfa::match_starts`, and the end by `Dfa::longest_match_at`.
+    pub fn find_at(&self, input: &[I], from: usize) -> Option<Match> {
+        self.find_with(input, &self.match_starts(input), from)
+    }
+
+    /// Successive non-overlapping leftmost-longest matches in `input`.
+    pub fn find_iter<'a>(&'a self, input: &'a [I]) -> impl Iterator<Item = Match> + 'a {
+        let starts = self.match_starts(input);
+        Matches::new(input.len(), move |at| self.find_with(input, &starts, at))
+    }
+
+    fn find_with(&self, input: &[I], starts: &[bool], from: usize) -> Option<Match> {
+        let start = (from..=input.len()).find(|&start| starts[start])?;
+        let end = self.longest_match_at(input, start);
+        Some(Match::new(start, end.expect("a match starts here")))
+    }
+
+    /// For every offset of `input` and its end, whether a match starts there.
+    /// Walking backwards from the end, it tracks the states from which the
+    /// rest of the input reaches an accept state, so it takes time linear in
+    /// the length of `input`.
+    fn match_starts(&self, input: &[I]) -> Vec<bool> {
+        let table = self.table();
+        let accepting = self.accepting(&table);
+        // previous[class][state] are the states moving to `state` on `class`
+        let mut previous = vec![vec![vec![]; table.states.len()]; table.classes.len()];
+        for (from, row) in table.next.iter().enumerate() {
+            for (class, to) in row.iter().enumerate() {
+                if let &Some(to) = to {
+                    previous[class][to].push(from);
+                }
+            }
+        }
+
+        let mut live = accepting.clone();
+        let mut starts = vec![false; input.len() + 1];
+        starts[input.len()] = live[0];
+        for at in (0..input.len()).rev() {
+            let mut before = accepting.clone();
+            if let Some(class) = table.class(input[at]) {
+                let states = (0..live.len()).filter(|&state| live[state]);
+                for &from in states.flat_map(|state| previous[class][state].iter()) {
+                    before[from] = true;
+                }
+            }
+            live = before;
+            starts[at] = live[0];
+        }
+        starts
+    }
+
+    /// Whether each state of `table` accepts.
+    fn accepting(&self, table: &Table<S, I>) -> Vec<bool> {
+        let states = table.states.iter();
+        states
+            .map(|state| self.accept_states.contains(state))
+            .collect()
+    }
+
+    /// The minimal DFA accepting the same language, computed with Hopcroft's
+    /// partition refinement. Missing transitions lead to an implicit dead
+    /// state, which is dropped again from the result along with every state
+    /// equivalent to it. States are numbered in BFS order from the start.
+    pub fn minimize(&self) -> Dfa<usize, I> {
+        let table = self.table();
+        let (dead, classes) = (table.states.len(), table.classes.len());
+        let target = |state: usize, class: usize| {
+            table
+                .next
+                .get(state)
+                .and_then(|row| row[class])
+                .unwrap_or(dead)
+        };
+        let accepting =
+            |state: usize| state != dead && self.accept_states.contains(&table.states[state]);
+        let tag = |state: usize| self.tags.get(&table.states[state]).cloned();
+
+        // inverse[class][state]: the states moving to `state` on `class`
+        let mut inverse = vec![vec![vec![]; dead + 1]; classes];
+        for state in 0..=dead {
+            for (class, inverse) in inverse.iter_mut().enumerate() {
+                inverse[target(state, class)].push(state);
+            }
+        }
+
+        // accept states with different tags are never merged
+        let mut initial = BTreeMap::<Option<Option<usize>>, Vec<usize>>::new();
+        for state in 0..=dead {
+            let key = Some(state).filter(|&state| accepting(state)).map(tag);
+            initial.entry(key).or_default().push(state);
+        }
+        let mut blocks = vec![];
+        let mut block_of = vec![0; dead + 1];
+        for block in initial.into_values() {
+            for &state in block.iter() {
+                block_of[state] = blocks.len();
+            }
+            blocks.push(block);
+        }
+
+        // (block, class) splitters still to be processed: every initial
+        // block but the largest
+        let mut pending = HashSet::new();
+        let largest = (0..blocks.len()).max_by_key(|&b| blocks[b].len()).unwrap();
+        for block in (0..blocks.len()).filter(|&block| block != largest) {
+            for class in 0..classes {
+                pending.insert((block, class));
+            }
+        }
+        let mut worklist = pending.iter().cloned().collect::<Vec<_>>();
+        while let Some((splitter, class)) = worklist.pop() {
+            pending.remove(&(splitter, class));
+            let mut touched = HashMap::<usize, Vec<usize>>::new();
+            for &state in blocks[splitter].iter() {
+                for &source in inverse[class][state].iter() {
+                    touched.entry(block_of[source]).or_default().push(source);
+                }
+            }
+            for (block, split) in touched {
+                if split.len() == blocks[block].len() {
+                    continue;
+                }
+                let new = blocks.len();
+                for &state in split.iter() {
+                    block_of[state] = new;
+                }
+                blocks[block].retain(|&state| block_of[state] == block);
+                blocks.push(split);
+                for class in 0..classes {
+                    let next = if pending.contains(&(block, class))
+                        || blocks[new].len() <= blocks[block].len()
+                    {
+                        (new, class)
+                    } else {
+                        (block, class)
+                    };
+                    if pending.insert(next) {
+                        worklist.push(next);
+                    }
+                }
+            }
+        }
+
+        let dead = block_of[dead];
+        let mut ids = HashMap::new();
+        ids.insert(block_of[0], 0);
+        let mut queue = vec![block_of[0]];
+        let mut transitions = HashMap::new();
+        let mut i = 0;
+        while i < queue.len() {
+            let block = queue[i];
+            let from = ids[&block];
+            let state = blocks[block][0];
+            i += 1;
+            if block == dead {
+                continue;
+            }
+            let mut edges: Vec<(I, I, usize)> = vec![];
+            for class in 0..classes {
+                let next = block_of[target(state, class)];
+                if next == dead {
+                    continue;
+                }
+                let id = *ids.entry(next).or_insert_with(|| {
+                    queue.push(next);
+                    queue.len() - 1
+                });
+                let (lo, hi) = table.classes[class];
+                push_edge(&mut edges, lo, hi, id);
+            }
+            for (lo, hi, to) in edges {
+                transitions.insert((from, Transition::range(lo, hi)), to);
+            }
+        }
+        let accept_states = ids
+            .iter()
+            .filter(|&(&block, _)| accepting(blocks[block][0]))
+            .map(|(_, &id)| id)
+            .collect();
+        let tags = ids
+            .iter()
+            .filter(|&(&block, _)| accepting(blocks[block][0]))
+            .filter_map(|(&block, &id)| tag(blocks[block][0]).map(|tag| (id, tag)))
+            .collect();
+        Dfa::new(0, accept_states, transitions).with_tags(tags)
+    }
+
+    /// Check whether `other` accepts the same language. If not, return the
+    /// shortest string accepted by exactly one of them, found by a
+    /// breadth-first walk over pairs of states.
+    pub fn equivalent<T>(&self, other: &Dfa<T, I>) -> Result<(), Vec<I>>
+    where
+        T: Hash + Eq + Clone,
+    {
+        let (left, right) = (self.table(), other.table());
+        let classes = alphabet::partition(left.classes.iter().chain(right.classes.iter()).cloned());
+        let accepting = |pair: (Option<usize>, Option<usize>)| {
+            (
+                pair.0
+                    .is_some_and(|state| self.accept_states.contains(&left.states[state])),
+                pair.1
+                    .is_some_and(|state| other.accept_states.contains(&right.states[state])),
+            )
+        };
+
+        let start = (Some(0), Some(0));
+        // pair => the pair and symbol it was first reached from
+        let mut parent = HashMap::new();
+        parent.insert(start, None);
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(pair) = queue.pop_front() {
+            let (l, r) = accepting(pair);
+            if l != r {
+                let mut input = vec![];
+                let mut pair = pair;
+                while let Some(&Some((from, symbol))) = parent.get(&pair) {
+                    input.push(symbol);
+                    pair = from;
+                }
+                input.reverse();
+                return Err(input);
+            }
+            for &(symbol, _) in classes.iter() {
+                let next = (
+                    pair.0.and_then(|state| left.step(state, symbol)),
+                    pair.1.and_then(|state| right.step(state, symbol)),
+                );
+                if next != (None, None) && !parent.contains_key(&next) {
+                    parent.insert(next, Some((pair, symbol)));
+                    queue.push_back(next);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The product automaton running `self` and `other` side by side. When
+    /// one side has no transition it moves to `None`, an implicit dead state,
+    /// so partial automata are handled exactly. A pair of states accepts when
+    /// `accept` holds for the acceptance of its two sides.
+    ///
+    /// Any combination is supported. When `accept(false, false)` holds, as
+    /// for NOR, the pair of dead states `(None, None)` accepts too and loops
+    /// on every symbol, so the result is taken over the full alphabet of `I`.
+    /// Use `Dfa::complete` first to restrict it to a smaller alphabet.
+    pub fn product<T, F>(&self, other: &Dfa<T, I>, accept: F) -> Dfa<(Option<S>, Option<T>), I>
+    where
+        T: Hash + Eq + Clone,
+        F: Fn(bool, bool) -> bool,
+    {
+        let (left, right) = (self.table(), other.table());
+        let both_dead = accept(false, false);
+        let mut classes =
+            alphabet::partition(left.classes.iter().chain(right.classes.iter()).cloned());
+        if both_dead {
+            // the dead pair needs a transition on the symbols of neither side
+            classes.extend(alphabet::complement(classes.clone()));
+            classes.sort();
+        }
+        let state = |pair: (Option<usize>, Option<usize>)| {
+            (
+                pair.0.map(|state| left.states[state].clone()),
+                pair.1.map(|state| right.states[state].clone()),
+            )
+        };
+
+        let start = (Some(0), Some(0));
+        let mut discovered = HashSet::new();
+        discovered.insert(start);
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        let mut accept_states = HashSet::new();
+        let mut transitions = HashMap::new();
+        while let Some(pair) = queue.pop_front() {
+            let accepting = accept(
+                pair.0
+                    .is_some_and(|state| self.accept_states.contains(&left.states[state])),
+                pair.1
+                    .is_some_and(|state| other.accept_states.contains(&right.states[state])),
+            );
+            if accepting {
+                accept_states.insert(state(pair));
+            }
+            let mut edges = vec![];
+            for &(lo, hi) in classes.iter() {
+                let next = (
+                    pair.0.and_then(|state| left.step(state, lo)),
+                    pair.1.and_then(|state| right.step(state, lo)),
+                );
+                if next == (None, None) && !both_dead {
+                    continue;
+                }
+                if discovered.insert(next) {
+                    queue.push_back(next);
+                }
+                push_edge(&mut edges, lo, hi, next);
+            }
+            for (lo, hi, next) in edges {
+                transitions.insert((state(pair), Transition::range(lo, hi)), state(next));
+            }
+        }
+        Dfa::new(state(start), accept_states, transitions)
+    }
+
+    /// The symbols appearing on any transition.
+    pub fn alphabet(&self) -> Alphabet<I> {
+        Alphabet::new(self.transitions.keys().map(|(_, input)| (input.0, input.1)))
+    }
+
+    /// A DFA with a transition on every symbol of `alphabet` from every
+    /// reachable state, missing transitions going to a new sink state `None`.
+    /// Transitions on symbols outside of `alphabet` are dropped, so the result
+    /// accepts the strings over `alphabet` that `self` accepts. It is
+    /// equivalent to `self` when `alphabet` covers `self.alphabet()`.
+    pub fn complete(&self, alphabet: &Alphabet<I>) -> Dfa<Option<S>, I> {
+        let table = self.table();
+        let classes = alphabet::partition(table.classes.iter().chain(alphabet.ranges()).cloned())
+            .into_iter()
+            .filter(|&(lo, _)| alphabet.contains(lo))
+            .collect::<Vec<_>>();
+        let state = |state: Option<usize>| state.map(|state| table.states[state].clone());
+
+        let mut discovered = HashSet::new();
+        discovered.insert(Some(0));
+        let mut queue = VecDeque::new();
+        queue.push_back(Some(0));
+        let mut transitions = HashMap::new();
+        while let Some(from) = queue.pop_front() {
+            let mut edges = vec![];
+            for &(lo, hi) in classes.iter() {
+                let next = from.and_then(|from| table.step(from, lo));
+                if discovered.insert(next) {
+                    queue.push_back(next);
+                }
+                push_edge(&mut edges, lo, hi, next);
+            }
+            for (lo, hi, next) in edges {
+                transitions.insert((state(from), Transition::range(lo, hi)), state(next));
+            }
+        }
+        let accept_states = self.accept_states.iter().cloned().map(Some).collect();
+        let tags = self
+            .tags
+            .iter()
+            .map(|(state, &tag)| (Some(state.clone()), tag));
+        Dfa::new(Some(self.start.clone()), accept_states, transitions).with_tags(tags.collect())
+    }
+
+    /// The strings over `alphabet` that are not accepted.
+    pub fn complement(&self, alphabet: &Alphabet<I>) -> Dfa<Option<S>, I> {
+        let mut dfa = self.complete(alphabet);
+        let mut states = HashSet::new();
+        states.insert(dfa.start.clone());
+        states.extend(dfa.transitions.values().cloned());
+        dfa.accept_states = states.difference(&dfa.accept_states).cloned().collect();
+        dfa
+    }
+
+    /// Strings accepted by both automata.
+    pub fn intersection<T>(&self, other: &Dfa<T, I>) -> Dfa<(Option<S>, Option<T>), I>
+    where
+        T: Hash + Eq + Clone,
+    {
+        self.product(other, |l, r| l && r)
+    }
+
+    /// Strings accepted by either automaton.
+    pub fn union<T>(&self, other: &Dfa<T, I>) -> Dfa<(Option<S>, Option<T>), I>
+    where
+        T: Hash + Eq + Clone,
+    {
+        self.product(other, |l, r| l || r)
+    }
+
+    /// Strings accepted by `self` but not by `other`.
+    pub fn difference<T>(&self, other: &Dfa<T, I>) -> Dfa<(Option<S>, Option<T>), I>
+    where
+        T: Hash + Eq + Clone,
+    {
+        self.product(other, |l, r| l && !r)
+    }
+
+    /// Strings accepted by exactly one of the automata.
+    pub fn symmetric_difference<T>(&self, other: &Dfa<T, I>) -> Dfa<(Option<S>, Option<T>), I>
+    where
+        T: Hash + Eq + Clone,
+    {
+        self.product(other, |l, r| l != r)
+    }
+}
+
+impl<S, I> Dfa<S, I>
+where
+    S: Hash + Eq + Clone + Debug,
+    I: Hash + Eq + Debug,
+{
+    /// Render the automaton in Graphviz DOT, see `dot::render`.
+    pub fn to_dot(&self) -> String {
+        let edges = self
+            .transitions
+            .iter()
+            .map(|((from, input), to)| {
+                (
+                    from.clone(),
+                    dot::range_label(&input.0, &input.1),
+                    to.clone(),
+                )
+            })
+            .collect();
+        dot::render(&self.start, &self.accept_states, edges)
+    }
+}
+
+impl<S, I> From<Dfa<S, I>> for Vm<I>
+where
+    S: Hash + Eq + Clone,
+    I: Letter,
+{
+    /// States are numbered in BFS order from the start, following the edges
+    /// of a state in the order of their symbols. Unreachable states are
+    /// dropped.
+    fn from(dfa: Dfa<S, I>) -> Self {
+        let mut edges = HashMap::<&S, Vec<(&Transition<I>, &S)>>::new();
+        for ((state, input), target) in dfa.transitions.iter() {
+            edges.entry(state).or_default().push((input, target));
+        }
+
+        let mut ids = HashMap::new();
+        ids.insert(&dfa.start, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(&dfa.start);
+        let mut transitions = HashMap::new();
+        while let Some(state) = queue.pop_front() {
+            let from = ids[state];
+            let mut edges = edges.remove(state).unwrap_or_default();
+            edges.sort_by_key(|(input, _)| input.0);
+            for (input, target) in edges {
+                let next = ids.len();
+                let to = *ids.entry(target).or_insert_with(|| {
+                    queue.push_back(target);
+                    next
+                });
+                transitions.insert((from, *input), to);
+            }
+        }
+
+        let accept_states = dfa
+            .accept_states
+            .iter()
+            .filter_map(|state| ids.get(state).cloned())
+            .collect();
+        let tags = dfa
+            .tags
+            .iter()
+            .filter_map(|(state, &tag)| ids.get(state).map(|&id| (id, tag)))
+            .collect();
+        Vm::new(0, accept_states, transitions).with_tags(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::{hashmap, hashset};
+    #[test]
+    fn test_dfa_run() {
+        let start = 0;
+        let accept_states = hashset! {2};
+        let transitions = hashmap! {
+            (0,Transition::new('a')) => 1,
+            (1,Transition::new('b')) => 2,
+            (2,Transition::new('c')) => 1,
+        };
+        let dfa = Dfa::new(start, accept_states, transitions);
+        assert_eq!(vec![0, 1, 2, 1, 2], dfa.run(&['a', 'b', 'c', 'b']).trace());
+    }
+    #[test]
+    fn test_dfa_run_range() {
+        let start = 0;
+        let accept_states = hashset! {1};
+        let transitions = hashmap! {
+            (0,Transition::range('a', 'z')) => 1,
+            (1,Transition::range('0', '9')) => 1,
+            (1,Transition::new('_')) => 0,
+        };
+        let mut dfa = Dfa::new(start, accept_states, transitions);
+        assert!(dfa.run(&['x', '4', '2']).accept());
+        assert!(dfa.run(&['x', '_', 'y', '7']).accept());
+        assert!(!dfa.run(&['x', '_']).accept());
+        assert!(!dfa.run(&['7']).accept());
+        assert_eq!(Some(&1), dfa.next(&0, &'a'));
+        assert_eq!(None, dfa.next(&1, &'/'));
+
+        // edges added after a lookup are seen by the next one
+        dfa.add_transition(((0, Transition::range('0', '9')), 1));
+        assert!(dfa.run(&['7']).accept());
+    }
+    #[test]
+    fn test_dfa_find() {
+        use crate::nfa::Nfa;
+        use crate::re::Re;
+        let dfa = Dfa::from(Nfa::from(Re::new("[0-9]+(\\.[0-9]+)?"))).minimize();
+        let text = "pi=3.14, e=2., n=42".chars().collect::<Vec<_>>();
+        assert_eq!(Some(Match::new(3, 7)), dfa.find(&text));
+        assert_eq!(Some(4), dfa.shortest_match(&text));
+        assert_eq!(Some(12), dfa.longest_match_at(&text, 11));
+        assert_eq!(None, dfa.longest_match_at(&text, 2));
+        assert_eq!(
+            vec![Match::new(3, 7), Match::new(11, 12), Match::new(17, 19)],
+            dfa.find_iter(&text).collect::<Vec<_>>()
+        );
+
+        let bytes = Dfa::new(
+            0,
+            hashset! {1},
+            hashmap! {(0,Transition::range(b'a', b'z')) => 1},
+        );
+        assert_eq!(Some(Match::new(2, 3)), bytes.find(b"!?x"));
+
+        for pattern in &["ab+|b|abcd", "abcd|c", "a*", "(a|b)*abb", "x?y?"] {
+            let nfa = Nfa::from(Re::new(pattern));
+            let dfa = Dfa::from(nfa.clone());
+            for text in &["xabbbcabcdb", "abcd", "baa", "aabababbab", "", "yyxy"] {
+                let text = text.chars().collect::<Vec<_>>();
+                assert_eq!(nfa.find(&text), dfa.find(&text));
+                assert_eq!(nfa.shortest_match(&text), dfa.shortest_match(&text));
+                assert!(nfa.find_iter(&text).eq(dfa.find_iter(&text)));
+            }
+        }
+
+        // every a could start a match, but none ends, which must not take
+        // quadratic time
+        let dfa = Dfa::from(Nfa::from(Re::new("a*b"))).minimize();
+        let mut text = vec!['a'; 100_000];
+        assert_eq!(None, dfa.find(&text));
+        assert_eq!(None, dfa.shortest_match(&text));
+        assert_eq!(0, dfa.find_iter(&text).count());
+        text.push('b');
+        assert_eq!(Some(Match::new(0, 100_001)), dfa.find_at(&text, 0));
+        assert_eq!(Some(Match::new(7, 100_001)), dfa.find_at(&text, 7));
+        assert_eq!(None, dfa.find_at(&text, 100_002));
+    }
+
+    #[test]
+    fn test_dfa_minimize() {
+        // (a|b)*abb before minimization, A and C are equivalent
+        let start = 'A';
+        let accept_states = hashset! {'E'};
+        let transitions = hashmap! {
+            ('A',Transition::new('a')) => 'B',
+            ('A',Transition::new('b')) => 'C',
+            ('B',Transition::new('a')) => 'B',
+            ('B',Transition::new('b')) => 'D',
+            ('C',Transition::new('a')) => 'B',
+            ('C',Transition::new('b')) => 'C',
+            ('D',Transition::new('a')) => 'B',
+            ('D',Transition::new('b')) => 'E',
+            ('E',Transition::new('a')) => 'B',
+            ('E',Transition::new('b')) => 'C',
+        };
+        let minimal = Dfa::new(
+            0,
+            hashset! {3},
+            hashmap! {
+                (0,Transition::new('a')) => 1,
+                (0,Transition::new('b')) => 0,
+                (1,Transition::new('a')) => 1,
+                (1,Transition::new('b')) => 2,
+                (2,Transition::new('a')) => 1,
+                (2,Transition::new('b')) => 3,
+                (3,Transition::new('a')) => 1,
+                (3,Transition::new('b')) => 0,
+            },
+        );
+        assert_eq!(
+            minimal,
+            Dfa::new(start, accept_states, transitions).minimize()
+        );
+        assert_eq!(minimal, minimal.minimize());
+    }
+
+    #[test]
+    fn test_dfa_minimize_partial() {
+        // 2 and 3 can never accept, and 4 is unreachable
+        let start = 0;
+        let accept_states = hashset! {1, 4};
+        let transitions = hashmap! {
+            (0,Transition::range('a', 'm')) => 1,
+            (0,Transition::range('n', 'z')) => 1,
+            (1,Transition::new('b')) => 2,
+            (2,Transition::new('b')) => 3,
+            (3,Transition::new('c')) => 2,
+            (4,Transition::new('a')) => 1,
+        };
+        let minimal = Dfa::new(
+            0,
+            hashset! {1},
+            hashmap! {
+                (0,Transition::range('a', 'z')) => 1,
+            },
+        );
+        assert_eq!(
+            minimal,
+            Dfa::new(start, accept_states, transitions).minimize()
+        );
+
+        // a missing transition and an explicit dead state are the same thing
+        let start = 0;
+        let accept_states = hashset! {0, 1};
+        let transitions = hashmap! {
+            (0,Transition::new('a')) => 1,
+            (0,Transition::new('b')) => 2,
+            (1,Transition::new('a')) => 0,
+            (2,Transition::new('a')) => 2,
+            (2,Transition::new('b')) => 2,
+        };
+        let minimal = Dfa::new(
+            0,
+            hashset! {0},
+            hashmap! {
+                (0,Transition::new('a')) => 0,
+            },
+        );
+        assert_eq!(
+            minimal,
+            Dfa::new(start, accept_states, transitions).minimize()
+        );
+
+        let empty = Dfa::new(0, hashset! {}, hashmap! {(0,Transition::new('a')) => 0});
+        assert_eq!(Dfa::new(0, hashset! {}, hashmap! {}), empty.minimize());
+    }
+
+    #[test]
+    fn test_dfa_equivalent() {
+        // a*b over {a, b} with a redundant copy of the a-loop
+        let dfa = Dfa::new(
+            0,
+            hashset! {2},
+            hashmap! {
+                (0,Transition::new('a')) => 1,
+                (0,Transition::new('b')) => 2,
+                (1,Transition::new('a')) => 0,
+                (1,Transition::new('b')) => 2,
+            },
+        );
+        let minimal = Dfa::new(
+            'x',
+            hashset! {'y'},
+            hashmap! {
+                ('x',Transition::new('a')) => 'x',
+                ('x',Transition::new('b')) => 'y',
+            },
+        );
+        assert_eq!(Ok(()), dfa.equivalent(&minimal));
+        assert_eq!(Ok(()), minimal.equivalent(&dfa.minimize()));
+
+        // also accepts a*bb
+        let mut other = minimal.clone();
+        other.add_transition((('y', Transition::new('b')), 'z'));
+        other.accept_states.insert('z');
+        assert_eq!(Err(vec!['b', 'b']), dfa.equivalent(&other));
+        assert_eq!(Err(vec!['b', 'b']), other.equivalent(&dfa));
+
+        // ranges are compared by the symbols they cover
+        let range = Dfa::new(
+            0,
+            hashset! {1},
+            hashmap! {(0,Transition::range('a', 'c')) => 1},
+        );
+        let split = Dfa::new(
+            0,
+            hashset! {1},
+            hashmap! {
+                (0,Transition::new('a')) => 1,
+                (0,Transition::range('b', 'c')) => 1,
+            },
+        );
+        assert_eq!(Ok(()), range.equivalent(&split));
+        let empty = Dfa::new(0, hashset! {}, hashmap! {});
+        assert_eq!(Err(vec!['a']), range.equivalent(&empty));
+    }
+
+    #[test]
+    fn test_dfa_product() {
+        use crate::nfa::Nfa;
+        use crate::re::Re;
+        let dfa = |pattern| Dfa::from(Nfa::from(Re::new(pattern))).minimize();
+        let check = |product: Dfa<(Option<usize>, Option<usize>), char>, pattern| {
+            assert_eq!(Ok(()), product.equivalent(&dfa(pattern)));
+        };
+        let (contains_a, ends_with_b) = (dfa("[ab]*a[ab]*"), dfa("[ab]*b"));
+        check(contains_a.intersection(&ends_with_b), "[ab]*a[ab]*b");
+        check(contains_a.union(&ends_with_b), "[ab]*(a[ab]*|b)");
+        check(contains_a.difference(&ends_with_b), "[ab]*a(a|())");
+        check(ends_with_b.difference(&contains_a), "b+");
+        check(
+            contains_a.symmetric_difference(&ends_with_b),
+            "[ab]*a(a|())|b+",
+        );
+
+        // the right side runs out of transitions
+        check(dfa("[ab]*").difference(&dfa("a*")), "[ab]*b[ab]*");
+        check(dfa("a*").symmetric_difference(&dfa("a+")), "()");
+        check(dfa("ab").union(&dfa("[c-z]d")), "ab|[c-z]d");
+        check(dfa("ab").intersection(&dfa("cd")), r"\0");
+
+        // both sides reject, so the dead pair accepts everything after it
+        let nor = dfa("a|ab").product(&dfa("b"), |l, r| !l && !r);
+        check(nor.clone(), "(?:[^ab]|a[^b]|b.|ab.).*|()");
+        assert!(nor.run(&['x', 'y']).accept() && !nor.run(&['a', 'b']).accept());
+        assert_eq!(
+            Some(&(None, None)),
+            nor.run(&['b', 'b', 'c']).trace().last()
+        );
+        check(dfa("[ab]*").product(&dfa("a"), |l, _| !l), ".*[^ab].*");
+
+        let pair = dfa("a").product(&dfa("b"), |l, r| l || r);
+        assert!(pair.run(&['a']).accept() && pair.run(&['b']).accept());
+        assert_eq!(
+            &[(Some(0), Some(0)), (Some(1), None)],
+            pair.run(&['a']).trace()
+        );
+    }
+
+    #[test]
+    fn test_dfa_complement() {
+        use crate::nfa::Nfa;
+        use crate::re::Re;
+        let re = |pattern| Re::new(pattern);
+        let dfa = |pattern| Dfa::from(Nfa::from(re(pattern)));
+
+        let ab = re("[ab]").alphabet();
+        let complete = dfa("a*b").complete(&ab);
+        assert_eq!(Ok(()), complete.equivalent(&dfa("a*b")));
+        // the sink is only reachable after the b
+        assert_eq!(Some(&None), complete.run(&['b', 'a']).trace().last());
+
+        let complement = dfa("a*b").complement(&ab);
+        assert_eq!(Ok(()), complement.equivalent(&dfa("a*|a*b[ab]+")));
+        assert_eq!(Ok(()), complement.complement(&ab).equivalent(&dfa("a*b")));
+
+        // the same language has a different complement over a larger alphabet
+        let abc = "abc".chars().collect();
+        let complement = dfa("[ab]*").complement(&abc);
+        assert_eq!(Ok(()), complement.equivalent(&dfa("[abc]*c[abc]*")));
+
+        // symbols outside of the alphabet are dropped
+        let complete = dfa("a|xyz").complete(&ab);
+        assert_eq!(Ok(()), complete.equivalent(&dfa("a")));
+        let complement = dfa("a|xyz").complement(&ab);
+        assert_eq!(Ok(()), complement.equivalent(&dfa("()|b[ab]*|a[ab]+")));
+
+        let complement = dfa(".*a.*").complement(&Alphabet::full());
+        assert_eq!(Ok(()), complement.equivalent(&dfa("[^a]*")));
+        assert_eq!(Alphabet::new(vec![('a', 'c')]), dfa("a|[bc]").alphabet());
+    }
+
+    #[test]
+    fn test_set_state() {
+        let (a, b) = (
+            SetState::new(hashset! {3, 1, 2}),
+            SetState::new(hashset! {1, 4}),
+        );
+        assert_eq!("{1, 2, 3}", a.to_string());
+        assert!(a < b);
+        assert_eq!(a, SetState::new(hashset! {2, 3, 1}));
+    }
+
+    #[test]
+    fn test_dfa_to_dot() {
+        let dfa = Dfa::new(
+            'p',
+            hashset! {'q'},
+            hashmap! {
+                ('p',Transition::new('a')) => 'q',
+                ('p',Transition::range('x', 'z')) => 'q',
+                ('q',Transition::new('"')) => 'p',
+            },
+        );
+        assert_eq!(
+            "digraph {
+\trankdir=LR;
+\tstart [shape=point];
+\t0 [label=\"'p'\", shape=circle];
+\t1 [label=\"'q'\", shape=doublecircle];
+\tstart -> 0;
+\t0 -> 1 [label=\"'a', 'x'-'z'\"];
+\t1 -> 0 [label=\"'\\\"'\"];
+}
+",
+            dfa.to_dot()
+        );
+    }
+
+    #[test]
+    fn test_dfa_to_vm() {
+        let start = 'a';
+        let accept_states = hashset! {'c'};
+        let transitions = hashmap! {
+            ('a',Transition::new('a')) => 'b',
+            ('a',Transition::new('b')) => 'c',
+            ('b',Transition::new('b')) => 'c',
+            ('c',Transition::new('c')) => 'b',
+        };
+        let dfa = Dfa::new(start, accept_states, transitions);
+        let vm = Vm::from(dfa);
+        assert_eq!(0, vm.start());
+        assert_eq!(&hashset! {2}, vm.accept_states());
+        let trace = vm.run(&['a', 'b', 'c', 'b']);
+        assert!(trace.accept());
+        assert_eq!(vec![0, 1, 2, 1, 2], *trace.trace());
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+/// Label of an edge consuming the symbols from `lo` to `hi`.
+pub fn range_label<I: Debug + PartialEq>(lo: &I, hi: &I) -> String {
+    if lo == hi {
+        format!("{:?}", lo)
+    } else {
+        format!("{:?}-{:?}", lo, hi)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render an automaton in Graphviz DOT. States are numbered in BFS order
+/// from `start` and labelled with their `Debug` output; the start state gets
+/// an incoming arrow and accept states are drawn as double circles. Parallel
+/// edges are merged into one edge listing all of their labels.
+pub fn render<S>(start: &S, accept_states: &HashSet<S>, edges: Vec<(S, String, S)>) -> String
+where
+    S: Hash + Eq + Clone + Debug,
+{
+    let mut edges = edges
+        .into_iter()
+        .map(|(from, label, to)| (format!("{:?}", from), label, format!("{:?}", to), from, to))
+        .collect::<Vec<_>>();
+    edges.sort_by(|a, b| (&a.0, &a.1, &a.2).cmp(&(&b.0, &b.1, &b.2)));
+    let mut outgoing = HashMap::<&S, Vec<&S>>::new();
+    for (_, _, _, from, to) in edges.iter() {
+        outgoing.entry(from).or_default().push(to);
+    }
+
+    let mut ids = HashMap::new();
+    ids.insert(start, 0);
+    let mut states = vec![start];
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(state) = queue.pop_front() {
+        for &next in outgoing.get(state).into_iter().flatten() {
+            if !ids.contains_key(next) {
+                ids.insert(next, states.len());
+                states.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    // states that cannot be reached still get drawn, after the others
+    let unreachable = edges
+        .iter()
+        .flat_map(|(_, _, _, from, to)| vec![from, to])
+        .chain(accept_states.iter())
+        .filter(|state| !ids.contains_key(state))
+        .map(|state| (format!("{:?}", state), state))
+        .collect::<BTreeMap<_, _>>();
+    for (_, state) in unreachable {
+        ids.insert(state, states.len());
+        states.push(state);
+    }
+
+    let mut merged = BTreeMap::<(usize, usize), Vec<&str>>::new();
+    for (_, label, _, from, to) in edges.iter() {
+        merged.entry((ids[from], ids[to])).or_default().push(label);
+    }
+
+    let mut dot = String::from("digraph {\n\trankdir=LR;\n\tstart [shape=point];\n");
+    for (id, state) in states.iter().enumerate() {
+        let shape = if accept_states.contains(state) {
+            "doublecircle"
+        } else {
+            "circle"
+        };
+        let label = escape(&format!("{:?}", state));
+        writeln!(dot, "\t{} [label=\"{}\", shape={}];", id, label, shape).unwrap();
+    }
+    dot.push_str("\tstart -> 0;\n");
+    for ((from, to), labels) in merged {
+        let label = escape(&labels.join(", "));
+        writeln!(dot, "\t{} -> {} [label=\"{}\"];", from, to, label).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashset;
+    #[test]
+    fn test_render() {
+        let edges = vec![
+            ("q1", "'b'".to_string(), "q0"),
+            ("q0", "'a'".to_string(), "q1"),
+            ("q0", "\"".to_string(), "q1"),
+            ("q2", "'c'".to_string(), "q1"),
+        ];
+        let dot = render(&"q0", &hashset! {"q1"}, edges);
+        assert_eq!(
+            "digraph {
+\trankdir=LR;
+\tstart [shape=point];
+\t0 [label=\"\\\"q0\\\"\", shape=circle];
+\t1 [label=\"\\\"q1\\\"\", shape=doublecircle];
+\t2 [label=\"\\\"q2\\\"\", shape=circle];
+\tstart -> 0;
+\t0 -> 1 [label=\"\\\", 'a'\"];
+\t1 -> 0 [label=\"'b'\"];
+\t2 -> 1 [label=\"'c'\"];
+}
+",
+            dot
+        );
+        assert_eq!("'a'-'z'", range_label(&'a', &'z'));
+        assert_eq!("'\\n'", range_label(&'\n', &'\n'));
+    }
+}
@@ -0,0 +1,12 @@
+//! The patterns and automata of `regular-language-utils`, in a crate of
+//! their own so that the `dfa!` macro can use them at compile time.
+
+pub mod alphabet;
+pub mod ast;
+pub mod automatan;
+pub mod class;
+pub mod dfa;
+pub mod dot;
+pub mod nfa;
+pub mod re;
+pub mod vm;
@@ -0,0 +1,869 @@
+use maplit::hashset;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::sync::OnceLock;
+
+use crate::alphabet::{self, Alphabet, Letter};
+use crate::automatan::{Match, Matches, Trace};
+use crate::dfa::{self, Dfa, SetState};
+use crate::dot;
+
+#[derive(Hash, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Transition<I> {
+    Epsilon,
+    Symbol(I),
+    /// Any symbol in the inclusive range.
+    Range(I, I),
+}
+
+impl<I> Transition<I> {
+    pub fn inner_symbol(self) -> Option<I> {
+        if let Transition::Symbol(c) = self {
+            Some(c)
+        } else {
+            None
+        }
+    }
+}
+
+impl<I: Clone + Ord> Transition<I> {
+    /// The consumed symbols as an inclusive range, `None` for epsilon.
+    pub fn range(&self) -> Option<(I, I)> {
+        match self {
+            Transition::Epsilon => None,
+            Transition::Symbol(c) => Some((c.clone(), c.clone())),
+            Transition::Range(lo, hi) => Some((lo.clone(), hi.clone())),
+        }
+    }
+
+    pub fn matches(&self, symbol: &I) -> bool {
+        match self {
+            Transition::Epsilon => false,
+            Transition::Symbol(c) => c == symbol,
+            Transition::Range(lo, hi) => lo <= symbol && symbol <= hi,
+        }
+    }
+}
+
+/// The symbol edges leaving each state, split into sorted disjoint ranges
+/// that each lead to every state an edge covering them leads to.
+type Index<S, I> = HashMap<S, Vec<(I, I, HashSet<S>)>>;
+
+#[derive(Clone)]
+pub struct Nfa<S, I>
+where
+    S: Hash + Eq,
+    I: Hash + Eq,
+{
+    start: S,
+    accept_states: HashSet<S>,
+    transitions: HashMap<(S, Transition<I>), HashSet<S>>,
+    tags: HashMap<S, usize>,
+    // built on first use, and dropped whenever the transitions change
+    index: OnceLock<Index<S, I>>,
+}
+
+impl<S, I> PartialEq for Nfa<S, I>
+where
+    S: Hash + Eq,
+    I: Hash + Eq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+            && self.accept_states == other.accept_states
+            && self.transitions == other.transitions
+            && self.tags == other.tags
+    }
+}
+
+impl<S, I> Debug for Nfa<S, I>
+where
+    S: Hash + Eq + Debug,
+    I: Hash + Eq + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Nfa")
+            .field("start", &self.start)
+            .field("accept_states", &self.accept_states)
+            .field("transitions", &self.transitions)
+            .field("tags", &self.tags)
+            .finish()
+    }
+}
+
+impl<S, I> Nfa<S, I>
+where
+    S: Hash + Eq,
+    I: Hash + Eq,
+{
+    pub fn new(
+        start: S,
+        accept_states: HashSet<S>,
+        transitions: HashMap<(S, Transition<I>), HashSet<S>>,
+    ) -> Self {
+        Self {
+            start,
+            accept_states,
+            transitions,
+            tags: HashMap::new(),
+            index: OnceLock::new(),
+        }
+    }
+
+    /// Tag accept states with the index of the rule they accept for.
+    pub fn with_tags(mut self, tags: HashMap<S, usize>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn add_transition(&mut self, transitions: ((S, Transition<I>), HashSet<S>)) {
+        let entry = self.transitions.entry(transitions.0).or_default();
+        entry.extend(transitions.1);
+        self.index.take();
+    }
+
+    pub fn get_transition<'a>(&'a self, input: &(S, Transition<I>)) -> Option<&'a HashSet<S>> {
+        self.transitions.get(input)
+    }
+
+    pub fn start(&self) -> &S {
+        &self.start
+    }
+
+    pub fn accept_states(&self) -> &HashSet<S> {
+        &self.accept_states
+    }
+
+    pub fn transitions(&self) -> &HashMap<(S, Transition<I>), HashSet<S>> {
+        &self.transitions
+    }
+
+    /// Rule indices of tagged accept states. Where several of them are
+    /// reached together, the smallest index wins.
+    pub fn tags(&self) -> &HashMap<S, usize> {
+        &self.tags
+    }
+}
+
+impl<S, I> Nfa<S, I>
+where
+    S: Hash + Eq + Clone,
+    I: Hash + Eq + Clone,
+{
+    pub fn concat(mut self, other: Self) -> Self {
+        use std::mem;
+        for state in mem::replace(&mut self.accept_states, other.accept_states) {
+            self.add_transition(((state, Transition::Epsilon), hashset! {other.start.clone()}));
+        }
+        self.transitions.extend(other.transitions);
+        self.index.take();
+        self.tags.extend(other.tags);
+        self
+    }
+
+    pub fn union(mut self, mut other: Self, start: S) -> Self {
+        use std::mem;
+        // merge two transitions function sets.
+        self.transitions.extend(mem::take(&mut other.transitions));
+        self.index.take();
+
+        self.add_transition((
+            (start.clone(), Transition::Epsilon),
+            hashset! {self.start.clone(),other.start.clone()},
+        ));
+
+        self.accept_states.extend(other.accept_states);
+        self.tags.extend(other.tags);
+        self.start = start;
+        self
+    }
+
+    pub fn star(mut self, start: S, accept: S) -> Self {
+        use std::mem;
+        self.add_transition((
+            (start.clone(), Transition::Epsilon),
+            hashset! {accept.clone(),self.start.clone()},
+        ));
+
+        for state in mem::take(&mut self.accept_states) {
+            self.add_transition((
+                (state.clone(), Transition::Epsilon),
+                hashset! {start.clone()},
+            ));
+        }
+
+        self.accept_states.insert(accept);
+        self.start = start;
+        self
+    }
+
+    /// One or more repetitions: like `star`, but `start` is not accepting.
+    pub fn plus(mut self, start: S, accept: S) -> Self {
+        use std::mem;
+        self.add_transition((
+            (start.clone(), Transition::Epsilon),
+            hashset! {self.start.clone()},
+        ));
+
+        for state in mem::take(&mut self.accept_states) {
+            self.add_transition((
+                (state.clone(), Transition::Epsilon),
+                hashset! {start.clone(), accept.clone()},
+            ));
+        }
+
+        self.accept_states.insert(accept);
+        self.start = start;
+        self
+    }
+
+    /// Zero or one occurrence, using `start` as a new accepting start state.
+    pub fn optional(mut self, start: S) -> Self {
+        self.add_transition((
+            (start.clone(), Transition::Epsilon),
+            hashset! {self.start.clone()},
+        ));
+        self.accept_states.insert(start.clone());
+        self.start = start;
+        self
+    }
+
+    /// Rename every state with `f`, which must be injective.
+    pub fn map_states<T, F>(&self, f: F) -> Nfa<T, I>
+    where
+        T: Hash + Eq,
+        F: Fn(&S) -> T,
+    {
+        Nfa::new(
+            f(&self.start),
+            self.accept_states.iter().map(&f).collect(),
+            self.transitions
+                .iter()
+                .map(|((state, input), targets)| {
+                    ((f(state), input.clone()), targets.iter().map(&f).collect())
+                })
+                .collect(),
+        )
+        .with_tags(
+            self.tags
+                .iter()
+                .map(|(state, &tag)| (f(state), tag))
+                .collect(),
+        )
+    }
+
+    /// extend a state set with epsilon edge
+    pub fn extend_set(nfa: &Nfa<S, I>, set: &HashSet<S>) -> HashSet<S> {
+        let mut extend = set.clone();
+        let mut queue = VecDeque::new();
+        queue.extend(extend.iter().cloned().collect::<VecDeque<_>>());
+        while !queue.is_empty() {
+            let state = queue.pop_front().unwrap();
+            let detected = nfa
+                .get_transition(&(state, Transition::Epsilon))
+                .cloned()
+                .unwrap_or_default();
+            for state in detected {
+                if extend.insert(state.clone()) {
+                    queue.push_back(state);
+                }
+            }
+        }
+        extend
+    }
+
+    /// An equivalent NFA without epsilon edges. Every state reachable from
+    /// the start keeps its name and gets the symbol edges of its epsilon
+    /// closure, and accepts, with the smallest tag, if its closure does.
+    /// States that can only be reached through epsilon edges are dropped.
+    pub fn remove_epsilons(&self) -> Self {
+        let mut edges = HashMap::<&S, Vec<(&Transition<I>, &HashSet<S>)>>::new();
+        for ((state, input), targets) in self.transitions.iter() {
+            if *input != Transition::Epsilon {
+                edges.entry(state).or_default().push((input, targets));
+            }
+        }
+
+        let mut result = Nfa::new(self.start.clone(), HashSet::new(), HashMap::new());
+        let mut seen = hashset! {self.start.clone()};
+        let mut queue = VecDeque::new();
+        queue.push_back(self.start.clone());
+        while let Some(state) = queue.pop_front() {
+            let closure = Nfa::extend_set(self, &hashset! {state.clone()});
+            if closure
+                .iter()
+                .any(|state| self.accept_states.contains(state))
+            {
+                result.accept_states.insert(state.clone());
+            }
+            if let Some(tag) = closure
+                .iter()
+                .filter_map(|state| self.tags.get(state))
+                .min()
+            {
+                result.tags.insert(state.clone(), *tag);
+            }
+            for &(input, targets) in closure.iter().flat_map(|state| edges.get(state)).flatten() {
+                for target in targets {
+                    if seen.insert(target.clone()) {
+                        queue.push_back(target.clone());
+                    }
+                }
+                result.add_transition(((state.clone(), input.clone()), targets.clone()));
+            }
+        }
+        result
+    }
+}
+
+impl<S, I> Nfa<S, I>
+where
+    S: Hash + Eq + Clone,
+    I: Hash + Letter,
+{
+    /// The states `state` moves to by consuming `symbol`, found by bisection
+    /// in the index of its edges.
+    fn targets(&self, state: &S, symbol: I) -> Option<&HashSet<S>> {
+        let index = self.index.get_or_init(|| {
+            let mut edges = HashMap::<&S, Vec<(I, I, &HashSet<S>)>>::new();
+            for ((state, input), targets) in self.transitions.iter() {
+                if let Some((lo, hi)) = input.range() {
+                    edges.entry(state).or_default().push((lo, hi, targets));
+                }
+            }
+            let mut index = Index::new();
+            for (state, edges) in edges {
+                let ranges = alphabet::partition(edges.iter().map(|&(lo, hi, _)| (lo, hi)));
+                let ranges = ranges.into_iter().map(|(lo, hi)| {
+                    let covering = edges.iter().filter(|edge| edge.0 <= lo && lo <= edge.1);
+                    let targets = covering.flat_map(|edge| edge.2.iter().cloned());
+                    (lo, hi, targets.collect())
+                });
+                index.insert(state.clone(), ranges.collect());
+            }
+            index
+        });
+        let ranges = index.get(state)?;
+        let range = ranges.partition_point(|&(_, hi, _)| hi < symbol);
+        ranges
+            .get(range)
+            .filter(|&&(lo, _, _)| lo <= symbol)
+            .map(|(_, _, targets)| targets)
+    }
+
+    /// States reachable from `set` by consuming `symbol`, before taking the
+    /// epsilon closure.
+    pub fn move_set(&self, set: &HashSet<S>, symbol: &I) -> HashSet<S> {
+        set.iter()
+            .filter_map(|state| self.targets(state, *symbol))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    pub fn run(&self, input: &[I]) -> Trace<HashSet<S>> {
+        let mut trace = vec![];
+        let mut set = Nfa::extend_set(self, &hashset! {self.start.clone()});
+
+        for symbol in input {
+            trace.push(set.clone());
+            set = Nfa::extend_set(self, &self.move_set(&set, symbol));
+        }
+        let accept = set.iter().any(|state| self.accept_states.contains(state));
+        trace.push(set);
+        Trace::new(accept, trace)
+    }
+
+    /// End of the first match anywhere in `input`, i.e. the smallest offset
+    /// at which some match ends.
+    pub fn shortest_match(&self, input: &[I]) -> Option<usize> {
+        let start = Nfa::extend_set(self, &hashset! {self.start.clone()});
+        let mut set = HashSet::new();
+        for at in 0..=input.len() {
+            set.extend(start.iter().cloned());
+            if set.iter().any(|state| self.accept_states.contains(state)) {
+                return Some(at);
+            }
+            if at < input.len() {
+                set = Nfa::extend_set(self, &self.move_set(&set, &input[at]));
+            }
+        }
+        None
+    }
+
+    /// The leftmost-longest match in `input`, see `Nfa::find_at`.
+    pub fn find(&self, input: &[I]) -> Option<Match> {
+        self.find_at(input, 0)
+    }
+
+    /// The leftmost-longest match starting at or after `from`: the match
+    /// with the smallest start, and among those the one ending last. Like
+    /// `run`, but each simulated state remembers the earliest offset a
+    /// thread through it started at.
+    pub fn find_at(&self, input: &[I], from: usize) -> Option<Match> {
+        let start = Nfa::extend_set(self, &hashset! {self.start.clone()});
+
+        let mut threads = HashMap::<S, usize>::new();
+        let mut best: Option<Match> = None;
+        for at in from..=input.len() {
+            // no later start can beat a match that was already found
+            if best.is_none() {
+                for state in start.iter() {
+                    threads.entry(state.clone()).or_insert(at);
+                }
+            }
+            for (state, &start) in threads.iter() {
+                let better = best.is_none_or(|best| {
+                    start < best.start() || (start == best.start() && at > best.end())
+                });
+                if better && self.accept_states.contains(state) {
+                    best = Some(Match::new(start, at));
+                }
+            }
+            if let Some(best) = best {
+                threads.retain(|_, start| *start <= best.start());
+            }
+            if at == input.len() || (best.is_some() && threads.is_empty()) {
+                break;
+            }
+
+            let mut next = HashMap::<S, usize>::new();
+            for (state, start) in threads {
+                if let Some(targets) = self.targets(&state, input[at]) {
+                    for target in Nfa::extend_set(self, targets) {
+                        let entry = next.entry(target).or_insert(start);
+                        *entry = start.min(*entry);
+                    }
+                }
+            }
+            threads = next;
+        }
+        best
+    }
+
+    /// Successive non-overlapping leftmost-longest matches in `input`.
+    pub fn find_iter<'a>(&'a self, input: &'a [I]) -> impl Iterator<Item = Match> + 'a {
+        Matches::new(input.len(), move |at| self.find_at(input, at))
+    }
+}
+
+impl<S, I> From<Nfa<S, I>> for Dfa<SetState<S>, I>
+where
+    S: Hash + Eq + Ord + Clone,
+    I: Hash + Letter,
+{
+    /// Subset construction over the state sets reachable from the epsilon
+    /// closure of the start state.
+    fn from(nfa: Nfa<S, I>) -> Dfa<SetState<S>, I> {
+        // symbols in the same class are never told apart by any transition
+        let classes = alphabet::partition(nfa.transitions.keys().filter_map(|(_, t)| t.range()));
+
+        let start = Nfa::extend_set(&nfa, &hashset! {nfa.start.clone()});
+        let mut discovered = hashset! {SetState::new(start.clone())};
+        let mut queue = VecDeque::new();
+        queue.push_back(start.clone());
+
+        let mut accept_states = HashSet::new();
+        let mut tags = HashMap::new();
+        let mut transitions = HashMap::new();
+        while let Some(set) = queue.pop_front() {
+            if !set.is_disjoint(&nfa.accept_states) {
+                accept_states.insert(SetState::new(set.clone()));
+            }
+            let tag = set
+                .iter()
+                .filter(|state| nfa.accept_states.contains(state))
+                .filter_map(|state| nfa.tags.get(state))
+                .min();
+            if let Some(&tag) = tag {
+                tags.insert(SetState::new(set.clone()), tag);
+            }
+            for &(lo, hi) in classes.iter() {
+                let next = nfa.move_set(&set, &lo);
+                if next.is_empty() {
+                    continue;
+                }
+                let next = Nfa::extend_set(&nfa, &next);
+                if discovered.insert(SetState::new(next.clone())) {
+                    queue.push_back(next.clone());
+                }
+                transitions.insert(
+                    (SetState::new(set.clone()), dfa::Transition::range(lo, hi)),
+                    SetState::new(next),
+                );
+            }
+        }
+        Dfa::new(SetState::new(start), accept_states, transitions).with_tags(tags)
+    }
+}
+
+impl<S, I> Nfa<S, I>
+where
+    S: Hash + Eq + Ord + Clone,
+    I: Hash + Letter,
+{
+    /// The symbols appearing on any transition.
+    pub fn alphabet(&self) -> Alphabet<I> {
+        Alphabet::new(
+            self.transitions
+                .keys()
+                .filter_map(|(_, input)| input.range()),
+        )
+    }
+
+    /// Check whether `other` accepts the same language, see
+    /// `Dfa::equivalent`.
+    pub fn equivalent<T>(&self, other: &Nfa<T, I>) -> Result<(), Vec<I>>
+    where
+        T: Hash + Eq + Ord + Clone,
+    {
+        Dfa::from(self.clone()).equivalent(&Dfa::from(other.clone()))
+    }
+}
+
+impl<S, I> Nfa<S, I>
+where
+    S: Hash + Eq + Clone + Debug,
+    I: Hash + Eq + Debug,
+{
+    /// Render the automaton in Graphviz DOT, see `dot::render`.
+    pub fn to_dot(&self) -> String {
+        let mut edges = vec![];
+        for ((from, input), targets) in self.transitions.iter() {
+            let label = match input {
+                Transition::Epsilon => "ε".to_string(),
+                Transition::Symbol(c) => format!("{:?}", c),
+                Transition::Range(lo, hi) => dot::range_label(lo, hi),
+            };
+            for to in targets {
+                edges.push((from.clone(), label.clone(), to.clone()));
+            }
+        }
+        dot::render(&self.start, &self.accept_states, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+    #[test]
+    fn test_nfa_run() {
+        let start = 0;
+        let accept_states = hashset! {3,5};
+        let transitions = hashmap! {
+            (0,Transition::Epsilon) => hashset!{1,4},
+            (1,Transition::Symbol('a')) => hashset!{2},
+            (2,Transition::Epsilon) => hashset!{3},
+            (4,Transition::Symbol('b')) => hashset!{5},
+        };
+        let nfa = Nfa::new(start, accept_states, transitions);
+
+        assert_eq!(
+            vec![hashset! {0,1,4}, hashset! {2,3}],
+            nfa.run(&['a']).trace()
+        );
+        assert!(nfa.run(&['a']).accept());
+        assert!(nfa.run(&['b']).accept());
+        assert!(!nfa.run(&['a', 'b']).accept());
+    }
+
+    #[test]
+    fn test_nfa_move_set() {
+        let mut nfa = Nfa::new(
+            0,
+            hashset! {3},
+            hashmap! {
+                (0,Transition::Symbol('m')) => hashset!{1},
+                (0,Transition::Range('a', 'z')) => hashset!{2},
+                (0,Transition::Range('k', 'p')) => hashset!{3},
+                (1,Transition::Range('a', 'c')) => hashset!{3},
+            },
+        );
+        assert_eq!(hashset! {1, 2, 3}, nfa.move_set(&hashset! {0, 2}, &'m'));
+        assert_eq!(hashset! {2, 3}, nfa.move_set(&hashset! {0, 1}, &'b'));
+        assert_eq!(hashset! {2}, nfa.move_set(&hashset! {0}, &'z'));
+        assert!(nfa.move_set(&hashset! {0, 1}, &'{').is_empty());
+
+        // edges added after a lookup are seen by the next one
+        nfa.add_transition(((2, Transition::Symbol('{')), hashset! {3}));
+        assert_eq!(hashset! {3}, nfa.move_set(&hashset! {2}, &'{'));
+    }
+
+    #[test]
+    fn test_nfa_find() {
+        use crate::re::Re;
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        let nfa = Nfa::from(Re::new("ab+|b|abcd"));
+        let text = chars("xabbbcabcdb");
+        assert_eq!(Some(Match::new(1, 5)), nfa.find(&text));
+        assert_eq!(Some(Match::new(6, 10)), nfa.find_at(&text, 5));
+        assert_eq!(Some(3), nfa.shortest_match(&text));
+        assert_eq!(
+            vec![Match::new(1, 5), Match::new(6, 10), Match::new(10, 11)],
+            nfa.find_iter(&text).collect::<Vec<_>>()
+        );
+        assert_eq!(None, nfa.find(&chars("xyz")));
+        assert_eq!(None, nfa.shortest_match(&chars("")));
+
+        // a longer match starting earlier wins over one that ends first
+        let nfa = Nfa::from(Re::new("abcd|c"));
+        assert_eq!(Some(Match::new(0, 4)), nfa.find(&chars("abcd")));
+        assert_eq!(Some(3), nfa.shortest_match(&chars("abcd")));
+
+        let nfa = Nfa::from(Re::new("a*"));
+        assert_eq!(
+            vec![Match::new(0, 0), Match::new(1, 3), Match::new(3, 3)],
+            nfa.find_iter(&chars("baa")).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_nfa_concat() {
+        let start = 1;
+        let accept_states = hashset! {2};
+        let transitions = hashmap! {
+            (1,Transition::Symbol('a')) => hashset!{2},
+        };
+        let nfa_a = Nfa::new(start, accept_states, transitions);
+
+        let start = 3;
+        let accept_states = hashset! {4};
+        let transitions = hashmap! {
+            (3,Transition::Symbol('b')) => hashset!{4},
+        };
+        let nfa_b = Nfa::new(start, accept_states, transitions);
+
+        let start = 1;
+        let accept_states = hashset! {4};
+        let transitions = hashmap! {
+            (1,Transition::Symbol('a')) => hashset!{2},
+            (2,Transition::Epsilon) => hashset!{3},
+            (3,Transition::Symbol('b')) => hashset!{4},
+        };
+
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            nfa_a.concat(nfa_b)
+        );
+    }
+
+    #[test]
+    fn test_nfa_alter() {
+        let start = 1;
+        let accept_states = hashset! {2};
+        let transitions = hashmap! {
+            (1,Transition::Symbol('a')) => hashset!{2},
+        };
+        let nfa_a = Nfa::new(start, accept_states, transitions);
+
+        let start = 3;
+        let accept_states = hashset! {4};
+        let transitions = hashmap! {
+            (3,Transition::Symbol('b')) => hashset!{4},
+        };
+        let nfa_b = Nfa::new(start, accept_states, transitions);
+
+        let start = 0;
+        let accept_states = hashset! {2,4};
+        let transitions = hashmap! {
+            (0,Transition::Epsilon) => hashset! {1,3},
+            (1,Transition::Symbol('a')) => hashset!{2},
+            (3,Transition::Symbol('b')) => hashset!{4},
+        };
+
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            nfa_a.union(nfa_b, 0)
+        );
+    }
+
+    #[test]
+    fn test_nfa_star() {
+        let start = 1;
+        let accept_states = hashset! {2};
+        let transitions = hashmap! {
+            (1,Transition::Symbol('a')) => hashset!{2},
+        };
+        let nfa_a = Nfa::new(start, accept_states, transitions);
+
+        let start = 0;
+        let accept_states = hashset! {3};
+        let transitions = hashmap! {
+            (0,Transition::Epsilon) => hashset!{1,3},
+            (1,Transition::Symbol('a')) => hashset!{2},
+            (2,Transition::Epsilon) => hashset!{0}
+        };
+
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            nfa_a.star(0, 3)
+        );
+    }
+
+    #[test]
+    fn test_nfa_plus_optional() {
+        let start = 1;
+        let accept_states = hashset! {2};
+        let transitions = hashmap! {
+            (1,Transition::Symbol('a')) => hashset!{2},
+        };
+        let nfa_a = Nfa::new(start, accept_states, transitions);
+
+        let start = 0;
+        let accept_states = hashset! {3};
+        let transitions = hashmap! {
+            (0,Transition::Epsilon) => hashset!{1},
+            (1,Transition::Symbol('a')) => hashset!{2},
+            (2,Transition::Epsilon) => hashset!{0,3}
+        };
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            nfa_a.clone().plus(0, 3)
+        );
+
+        let start = 0;
+        let accept_states = hashset! {0,2};
+        let transitions = hashmap! {
+            (0,Transition::Epsilon) => hashset!{1},
+            (1,Transition::Symbol('a')) => hashset!{2},
+        };
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            nfa_a.clone().optional(0)
+        );
+
+        let start = 11;
+        let accept_states = hashset! {12};
+        let transitions = hashmap! {
+            (11,Transition::Symbol('a')) => hashset!{12},
+        };
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            nfa_a.map_states(|state| state + 10)
+        );
+    }
+
+    #[test]
+    fn test_nfa_equivalent() {
+        let nfa = Nfa::new(
+            0,
+            hashset! {1},
+            hashmap! {
+                (0,Transition::Symbol('a')) => hashset!{0,1},
+            },
+        );
+        let plus = Nfa::new(
+            0,
+            hashset! {1},
+            hashmap! {
+                (0,Transition::Symbol('a')) => hashset!{1},
+                (1,Transition::Epsilon) => hashset!{0},
+            },
+        );
+        assert_eq!(Ok(()), nfa.equivalent(&plus));
+        assert_eq!(Err(vec![]), nfa.equivalent(&plus.optional(2)));
+    }
+
+    #[test]
+    fn test_nfa_remove_epsilons() {
+        use crate::re::Re;
+        let transitions = hashmap! {
+            (2,Transition::Symbol('a')) => hashset!{1},
+            (2,Transition::Symbol('b')) => hashset!{5},
+            (1,Transition::Symbol('a')) => hashset!{1},
+            (1,Transition::Symbol('b')) => hashset!{5},
+            (5,Transition::Symbol('b')) => hashset!{5},
+        };
+        assert_eq!(
+            Nfa::new(2, hashset! {1, 2, 5}, transitions),
+            Nfa::from(Re::new("a*b*")).remove_epsilons()
+        );
+
+        for pattern in &["(a|b)*abb", "(ab|a)?[0-9]{1,3}", "()", r"\0|(?:x*)*"] {
+            let nfa = Nfa::from(Re::new(pattern));
+            let result = nfa.remove_epsilons();
+            let mut inputs = result.transitions().keys().map(|(_, input)| input);
+            assert!(inputs.all(|input| *input != Transition::Epsilon));
+            assert_eq!(Ok(()), nfa.equivalent(&result));
+        }
+
+        // accept states reached together keep the smallest tag
+        let nfa = Nfa::new(
+            0,
+            hashset! {1, 2},
+            hashmap! {
+                (0,Transition::Epsilon) => hashset!{1, 2},
+                (3,Transition::Symbol('a')) => hashset!{0},
+            },
+        )
+        .with_tags(hashmap! {1 => 4, 2 => 3});
+        // and states only reachable through epsilon edges are dropped
+        let result = nfa.remove_epsilons();
+        assert_eq!(&hashset! {0}, result.accept_states());
+        assert!(result.transitions().is_empty());
+        assert_eq!(&hashmap! {0 => 3}, result.tags());
+    }
+
+    #[test]
+    fn test_nfa_to_dot() {
+        let nfa = Nfa::new(
+            0,
+            hashset! {2},
+            hashmap! {
+                (0,Transition::Epsilon) => hashset!{1, 2},
+                (1,Transition::Symbol('a')) => hashset!{2},
+                (1,Transition::Range('0', '9')) => hashset!{2},
+            },
+        );
+        let dot = nfa.to_dot();
+        assert!(dot.contains("\t0 [label=\"0\", shape=circle];\n"));
+        assert!(dot.contains("\t2 [label=\"2\", shape=doublecircle];\n"));
+        assert!(dot.contains("\tstart -> 0;\n"));
+        assert!(dot.contains("\t0 -> 2 [label=\"ε\"];\n"));
+        assert!(dot.contains("\t1 -> 2 [label=\"'0'-'9', 'a'\"];\n"));
+    }
+
+    #[test]
+    fn test_nfa_to_dfa() {
+        let start = 0;
+        let accept_states = hashset! {2};
+        let transitions = hashmap! {
+            (0, Transition::Epsilon) => hashset! {1,3},
+            (1, Transition::Symbol('a')) => hashset! {2},
+        };
+        let nfa = Nfa::new(start, accept_states, transitions);
+        dbg!(Dfa::from(nfa));
+
+        let start = 0;
+        let accept_states = hashset! {3};
+        let transitions = hashmap! {
+            (0, Transition::Epsilon) => hashset! {1},
+            (1, Transition::Range('a', 'z')) => hashset! {1, 2},
+            (1, Transition::Symbol('x')) => hashset! {3},
+            (2, Transition::Symbol('x')) => hashset! {3},
+            (4, Transition::Symbol('x')) => hashset! {3},
+        };
+        let nfa = Nfa::new(start, accept_states, transitions);
+        let set = |states| SetState::new(states);
+        // {4} is never reached and does not become a state
+        let dfa = Dfa::new(
+            set(hashset! {0, 1}),
+            hashset! {set(hashset! {1, 2, 3})},
+            hashmap! {
+                (set(hashset! {0, 1}), dfa::Transition::range('a', 'w')) => set(hashset! {1, 2}),
+                (set(hashset! {0, 1}), dfa::Transition::new('x')) => set(hashset! {1, 2, 3}),
+                (set(hashset! {0, 1}), dfa::Transition::range('y', 'z')) => set(hashset! {1, 2}),
+                (set(hashset! {1, 2}), dfa::Transition::range('a', 'w')) => set(hashset! {1, 2}),
+                (set(hashset! {1, 2}), dfa::Transition::new('x')) => set(hashset! {1, 2, 3}),
+                (set(hashset! {1, 2}), dfa::Transition::range('y', 'z')) => set(hashset! {1, 2}),
+                (set(hashset! {1, 2, 3}), dfa::Transition::range('a', 'w')) => set(hashset! {1, 2}),
+                (set(hashset! {1, 2, 3}), dfa::Transition::new('x')) => set(hashset! {1, 2, 3}),
+                (set(hashset! {1, 2, 3}), dfa::Transition::range('y', 'z')) => set(hashset! {1, 2}),
+            },
+        );
+        assert_eq!(dfa, Dfa::from(nfa));
+    }
+}
@@ -0,0 +1,2023 @@
+use crate::alphabet::{self, Alphabet, Letter};
+use crate::ast::Ast;
+use crate::class::CharClass;
+use crate::dfa::{self, Dfa};
+use crate::nfa::{Nfa, Transition};
+use maplit::{btreeset, hashmap, hashset};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::{Enumerate, Peekable};
+use std::rc::Rc;
+use std::str::FromStr;
+use ReOperator::*;
+use ReToken::*;
+
+type Chars<'a> = Peekable<Enumerate<std::str::Chars<'a>>>;
+
+/// The largest count a counted repetition `{m,n}` may have.
+pub const MAX_REPETITION: usize = 1000;
+
+/// The largest size a pattern may have once its counted repetitions are
+/// unrolled, see `unrolled_size`. Every repetition is a copy of the repeated
+/// automaton, so nested counts would otherwise multiply until memory runs out.
+pub const MAX_UNROLLED_SIZE: usize = 100_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum ReOperator {
+    Concat,
+    Alter,
+    Star,
+    Plus,
+    Question,
+    /// `{m}`, `{m,}` or `{m,n}`: at least `m` and at most `n` repetitions.
+    Repeat(usize, Option<usize>),
+    Left,
+    Right,
+}
+
+impl ReOperator {
+    pub fn priority(self) -> i32 {
+        match self {
+            Left | Right => 0,
+            Alter => 1,
+            Concat => 2,
+            Star | Plus | Question | Repeat(..) => 3,
+        }
+    }
+    pub fn eval(self, ctx: &mut Vec<Ast<ReToken>>) {
+        let pcnt = match self {
+            Concat | Alter => 2,
+            Star | Plus | Question | Repeat(..) => 1,
+            Left | Right => 0,
+        };
+        let mut children = vec![];
+        for _ in 0..pcnt {
+            children.push(Rc::new(ctx.pop().unwrap()));
+        }
+        children.reverse();
+        let children = if !children.is_empty() {
+            Some(children)
+        } else {
+            None
+        };
+        ctx.push(Ast::new(Operator(self), children));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReToken {
+    Symbol(char),
+    Class(CharClass),
+    /// The empty string, written `()`.
+    Epsilon,
+    /// The empty language, written `\0`.
+    EmptySet,
+    Operator(ReOperator),
+    /// A capture group around its single child, numbered from 1 in the
+    /// order of the opening parentheses. Written `(...)`, or `(?<name>...)`
+    /// to also name it; `(?:...)` groups without capturing.
+    Capture(usize, Option<String>),
+}
+
+impl ReToken {
+    pub fn new(c: char) -> Self {
+        match c {
+            '*' => Operator(Star),
+            '+' => Operator(Plus),
+            '?' => Operator(Question),
+            '|' => Operator(Alter),
+            '(' => Operator(Left),
+            ')' => Operator(Right),
+            '.' => Class(CharClass::any()),
+            _ => Symbol(c),
+        }
+    }
+    /// Translate the character following a `\` into the token it stands for.
+    /// Punctuation is taken literally, letters and digits are reserved.
+    pub fn escape(c: char) -> Option<Self> {
+        match c {
+            '0' => Some(EmptySet),
+            'n' => Some(Symbol('\n')),
+            't' => Some(Symbol('\t')),
+            'r' => Some(Symbol('\r')),
+            _ if c.is_alphanumeric() => None,
+            _ => Some(Symbol(c)),
+        }
+    }
+    pub fn is_operator(&self) -> bool {
+        !self.is_symbol()
+    }
+    /// Whether the token is a leaf: a symbol, a class of symbols, the empty
+    /// string or the empty language.
+    pub fn is_symbol(&self) -> bool {
+        matches!(self, Symbol(_) | Class(_) | Epsilon | EmptySet)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReErrorKind {
+    /// A `(` that is never closed.
+    UnmatchedLeft,
+    /// A `)` without a matching `(`.
+    UnmatchedRight,
+    /// An operator with no operand to apply to, e.g. a leading `*`.
+    DanglingOperator,
+    /// An alternative without any symbol, e.g. `a|`, `|a` or `(|a)`.
+    EmptyAlternative,
+    /// A pattern without any symbol.
+    EmptyPattern,
+    /// A `\` followed by nothing or by an unknown escape.
+    InvalidEscape,
+    /// A `[` that is never closed.
+    UnmatchedBracket,
+    /// A class without any member, e.g. `[]`.
+    EmptyClass,
+    /// A class range whose bounds are out of order, e.g. `[z-a]`.
+    InvalidRange,
+    /// A malformed `{m,n}`, one with `m` greater than `n`, one with a count
+    /// over `MAX_REPETITION`, or one unrolling the pattern past
+    /// `MAX_UNROLLED_SIZE`.
+    InvalidRepetition,
+    /// A `(?` not followed by `:` or by a valid `<name>`.
+    InvalidGroup,
+    /// A group name that was already used, e.g. `(?<x>a)(?<x>b)`.
+    DuplicateGroupName,
+}
+
+/// Error returned by `Re::parse`, pointing at the offending character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReError {
+    kind: ReErrorKind,
+    offset: usize,
+}
+
+impl ReError {
+    pub fn new(kind: ReErrorKind, offset: usize) -> Self {
+        Self { kind, offset }
+    }
+    pub fn kind(&self) -> ReErrorKind {
+        self.kind
+    }
+    /// Offset of the offending character, counted in `char`s.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ReError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.kind {
+            ReErrorKind::UnmatchedLeft => "unmatched `(`",
+            ReErrorKind::UnmatchedRight => "unmatched `)`",
+            ReErrorKind::DanglingOperator => "operator without operand",
+            ReErrorKind::EmptyAlternative => "empty alternative",
+            ReErrorKind::EmptyPattern => "empty pattern",
+            ReErrorKind::InvalidEscape => "invalid escape sequence",
+            ReErrorKind::UnmatchedBracket => "unmatched `[`",
+            ReErrorKind::EmptyClass => "empty character class",
+            ReErrorKind::InvalidRange => "invalid character range",
+            ReErrorKind::InvalidRepetition => "invalid repetition bounds",
+            ReErrorKind::InvalidGroup => "invalid group syntax",
+            ReErrorKind::DuplicateGroupName => "duplicate group name",
+        };
+        write!(f, "{} at offset {}", message, self.offset)
+    }
+}
+
+impl Error for ReError {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Re {
+    ast: Ast<ReToken>,
+}
+
+impl Re {
+    /// Parse a pattern, panicking if it is malformed. See `Re::parse`.
+    pub fn new(pattern: &str) -> Self {
+        match Re::parse(pattern) {
+            Ok(re) => re,
+            Err(err) => panic!("invalid pattern {:?}: {}", pattern, err),
+        }
+    }
+
+    pub fn parse(pattern: &str) -> Result<Self, ReError> {
+        use ReErrorKind::*;
+        // operators waiting for their right operand, with their offsets
+        let mut ops: Vec<(ReOperator, usize)> = vec![];
+        let mut asts: Vec<Ast<ReToken>> = vec![];
+        // for every open `(`, the capture to wrap its contents in, if any
+        let mut groups: Vec<Option<ReToken>> = vec![];
+        // whether the previous token completes an operand: a symbol, `)` or `*`
+        let mut operand = false;
+
+        for (offset, token) in Re::tokenize(pattern)? {
+            match token {
+                Operator(Left) | Capture(..) => {
+                    if operand {
+                        Re::push_operator(Concat, offset, &mut ops, &mut asts);
+                    }
+                    ops.push((Left, offset));
+                    groups.push(Some(token).filter(|token| *token != Operator(Left)));
+                    operand = false;
+                }
+                Operator(Right) => {
+                    if let Some((Left, _)) = ops.last().filter(|_| !operand) {
+                        // `()` right after its `(` is the empty string
+                        asts.push(Ast::new(Epsilon, None));
+                        operand = true;
+                    } else if !operand {
+                        return Err(ReError::new(EmptyAlternative, offset));
+                    }
+                    loop {
+                        match ops.pop() {
+                            Some((Left, _)) => break,
+                            Some((func, _)) => func.eval(&mut asts),
+                            None => return Err(ReError::new(UnmatchedRight, offset)),
+                        }
+                    }
+                    if let Some(capture) = groups.pop().unwrap() {
+                        let child = Rc::new(asts.pop().unwrap());
+                        asts.push(Ast::new(capture, Some(vec![child])));
+                    }
+                }
+                Operator(Alter) => {
+                    if !operand {
+                        return Err(ReError::new(EmptyAlternative, offset));
+                    }
+                    Re::push_operator(Alter, offset, &mut ops, &mut asts);
+                    operand = false;
+                }
+                Operator(func) => {
+                    // postfix operators bind tighter than anything else
+                    if !operand {
+                        return Err(ReError::new(DanglingOperator, offset));
+                    }
+                    func.eval(&mut asts);
+                    if let Repeat(..) = func {
+                        if unrolled_size(asts.last().unwrap()) > MAX_UNROLLED_SIZE {
+                            return Err(ReError::new(InvalidRepetition, offset));
+                        }
+                    }
+                }
+                leaf => {
+                    if operand {
+                        Re::push_operator(Concat, offset, &mut ops, &mut asts);
+                    }
+                    asts.push(Ast::new(leaf, None));
+                    operand = true;
+                }
+            }
+        }
+
+        if let Some(&(_, offset)) = ops.iter().rev().find(|(func, _)| *func == Left) {
+            return Err(ReError::new(UnmatchedLeft, offset));
+        }
+        if !operand {
+            let kind = if asts.is_empty() {
+                EmptyPattern
+            } else {
+                EmptyAlternative
+            };
+            return Err(ReError::new(kind, pattern.chars().count()));
+        }
+        while let Some((func, _)) = ops.pop() {
+            func.eval(&mut asts);
+        }
+        Ok(Re {
+            ast: asts.pop().unwrap(),
+        })
+    }
+
+    /// Split a pattern into tokens, each paired with its offset.
+    fn tokenize(pattern: &str) -> Result<Vec<(usize, ReToken)>, ReError> {
+        let mut tokens = vec![];
+        // the number of capture groups so far and their names
+        let mut captures = 0;
+        let mut names = HashSet::new();
+        let mut chars = pattern.chars().enumerate().peekable();
+        while let Some((offset, c)) = chars.next() {
+            let token = match c {
+                '(' => match Re::group(&mut chars, offset, captures + 1)? {
+                    Capture(_, Some(name)) if !names.insert(name.clone()) => {
+                        return Err(ReError::new(ReErrorKind::DuplicateGroupName, offset));
+                    }
+                    Capture(index, name) => {
+                        captures = index;
+                        Capture(index, name)
+                    }
+                    token => token,
+                },
+                '\\' => chars
+                    .next()
+                    .and_then(|(_, c)| ReToken::escape(c))
+                    .ok_or_else(|| ReError::new(ReErrorKind::InvalidEscape, offset))?,
+                '[' => Class(Re::class(&mut chars, offset)?),
+                '{' => Operator(Re::repetition(&mut chars, offset)?),
+                _ => ReToken::new(c),
+            };
+            tokens.push((offset, token));
+        }
+        Ok(tokens)
+    }
+
+    /// Parse the opening of a group whose `(` is at `offset`: a capture
+    /// numbered `index`, possibly named, or `(?:` which only groups.
+    fn group(chars: &mut Chars, offset: usize, index: usize) -> Result<ReToken, ReError> {
+        let error = || ReError::new(ReErrorKind::InvalidGroup, offset);
+        if chars.next_if(|&(_, c)| c == '?').is_none() {
+            return Ok(Capture(index, None));
+        }
+        match chars.next() {
+            Some((_, ':')) => Ok(Operator(Left)),
+            Some((_, '<')) => {
+                let mut name = String::new();
+                loop {
+                    match chars.next().ok_or_else(error)? {
+                        (_, '>') if !name.is_empty() => break,
+                        (_, c) if c == '_' || c.is_alphabetic() => name.push(c),
+                        (_, c) if c.is_ascii_digit() && !name.is_empty() => name.push(c),
+                        _ => return Err(error()),
+                    }
+                }
+                Ok(Capture(index, Some(name)))
+            }
+            _ => Err(error()),
+        }
+    }
+
+    /// Parse the members of a bracketed class whose `[` is at `offset`.
+    fn class(chars: &mut Chars, offset: usize) -> Result<CharClass, ReError> {
+        use ReErrorKind::*;
+        let negated = chars.next_if(|&(_, c)| c == '^').is_some();
+        let mut items = vec![];
+        loop {
+            let (at, c) = chars
+                .next()
+                .ok_or_else(|| ReError::new(UnmatchedBracket, offset))?;
+            let lo = match c {
+                ']' => break,
+                _ => Re::class_member(chars, at, c)?,
+            };
+            // a `-` between two members forms a range, elsewhere it is literal
+            let mut lookahead = chars.clone();
+            let hi = match (lookahead.next(), lookahead.next()) {
+                (Some((_, '-')), Some((at, c))) if c != ']' => {
+                    chars.nth(1);
+                    let hi = Re::class_member(chars, at, c)?;
+                    if hi < lo {
+                        return Err(ReError::new(InvalidRange, at));
+                    }
+                    hi
+                }
+                _ => lo,
+            };
+            items.push((lo, hi));
+        }
+        if items.is_empty() {
+            return Err(ReError::new(EmptyClass, offset));
+        }
+        Ok(CharClass::new(negated, items))
+    }
+
+    /// Parse the bounds of a counted repetition whose `{` is at `offset`.
+    fn repetition(chars: &mut Chars, offset: usize) -> Result<ReOperator, ReError> {
+        let error = || ReError::new(ReErrorKind::InvalidRepetition, offset);
+        // `None` if there are no digits
+        let number = |chars: &mut Chars| {
+            let mut digits = String::new();
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+            match digits.parse::<usize>() {
+                Ok(count) if count <= MAX_REPETITION => Ok(Some(count)),
+                _ if digits.is_empty() => Ok(None),
+                _ => Err(error()),
+            }
+        };
+        let min = number(chars)?.ok_or_else(error)?;
+        let max = if chars.next_if(|&(_, c)| c == ',').is_some() {
+            number(chars)?
+        } else {
+            Some(min)
+        };
+        match chars.next() {
+            Some((_, '}')) if max.is_none_or(|max| min <= max) => Ok(Repeat(min, max)),
+            _ => Err(error()),
+        }
+    }
+
+    /// Resolve a class member starting with `c`, consuming its escape if any.
+    fn class_member(chars: &mut Chars, offset: usize, c: char) -> Result<char, ReError> {
+        if c != '\\' {
+            return Ok(c);
+        }
+        match chars.next().and_then(|(_, c)| ReToken::escape(c)) {
+            Some(Symbol(c)) => Ok(c),
+            _ => Err(ReError::new(ReErrorKind::InvalidEscape, offset)),
+        }
+    }
+
+    /// Reduce every pending operator binding at least as tight as `func`,
+    /// then push `func` itself.
+    fn push_operator(
+        func: ReOperator,
+        offset: usize,
+        ops: &mut Vec<(ReOperator, usize)>,
+        asts: &mut Vec<Ast<ReToken>>,
+    ) {
+        while let Some(&(top, _)) = ops.last() {
+            if top == Left || top.priority() < func.priority() {
+                break;
+            }
+            ops.pop();
+            top.eval(asts);
+        }
+        ops.push((func, offset));
+    }
+
+    pub fn ast(&self) -> &Ast<ReToken> {
+        &self.ast
+    }
+
+    /// The symbols the pattern mentions, either directly or through a class.
+    pub fn alphabet(&self) -> Alphabet<char> {
+        fn ranges(ast: &Ast<ReToken>, result: &mut Vec<(char, char)>) {
+            match ast.token() {
+                &Symbol(c) => result.push((c, c)),
+                Class(class) => result.extend(class.ranges()),
+                _ => {}
+            }
+            for child in ast.children().into_iter().flatten() {
+                ranges(child, result);
+            }
+        }
+        let mut result = vec![];
+        ranges(self.ast(), &mut result);
+        Alphabet::new(result)
+    }
+
+    /// Check whether `other` describes the same language. If not, return the
+    /// shortest string matched by exactly one of them.
+    pub fn equivalent(&self, other: &Re) -> Result<(), String> {
+        let (left, right) = (Nfa::from(self.clone()), Nfa::from(other.clone()));
+        left.equivalent(&right)
+            .map_err(|input| input.into_iter().collect())
+    }
+}
+
+impl FromStr for Re {
+    type Err = ReError;
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Re::parse(pattern)
+    }
+}
+
+impl fmt::Display for ReOperator {
+    /// Postfix operators as they are written, the others by name.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Concat => write!(f, "concat"),
+            Alter => write!(f, "alter"),
+            Star => write!(f, "*"),
+            Plus => write!(f, "+"),
+            Question => write!(f, "?"),
+            Repeat(min, Some(max)) if min == max => write!(f, "{{{}}}", min),
+            Repeat(min, Some(max)) => write!(f, "{{{},{}}}", min, max),
+            Repeat(min, None) => write!(f, "{{{},}}", min),
+            Left => write!(f, "("),
+            Right => write!(f, ")"),
+        }
+    }
+}
+
+impl fmt::Display for ReToken {
+    /// Symbols and classes as they are written in a pattern, which is what
+    /// `Re` is displayed with, and the other tokens as labels for the
+    /// printers of `Ast`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Symbol(c) => write_symbol(f, c, "()[]{}*+?|.\\"),
+            Class(class) if class.negated() && class.items().is_empty() => write!(f, "."),
+            Class(class) if class.items().is_empty() => write!(f, "\\0"),
+            Class(class) => {
+                write!(f, "[{}", if class.negated() { "^" } else { "" })?;
+                for &(lo, hi) in class.items() {
+                    write_symbol(f, lo, "[]^-\\")?;
+                    if lo != hi {
+                        write!(f, "-")?;
+                        write_symbol(f, hi, "[]^-\\")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Epsilon => write!(f, "ε"),
+            EmptySet => write!(f, "∅"),
+            Operator(operator) => write!(f, "{}", operator),
+            Capture(index, None) => write!(f, "group {}", index),
+            Capture(index, Some(name)) => write!(f, "group {} <{}>", index, name),
+        }
+    }
+}
+
+impl fmt::Display for Re {
+    /// Write the pattern back as text, parenthesizing an operand only where
+    /// the priorities of the operators require it. Parentheses that only
+    /// group are written `(?:...)`, so the text parses to the same tree.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_ast(f, self.ast())
+    }
+}
+
+/// The priority an operand written for `ast` binds with.
+fn priority(ast: &Ast<ReToken>) -> i32 {
+    match ast.token() {
+        &Operator(operator) => operator.priority(),
+        _ => Star.priority() + 1,
+    }
+}
+
+fn write_ast(f: &mut fmt::Formatter, ast: &Ast<ReToken>) -> fmt::Result {
+    let children = ast.children();
+    // an operand binding looser than `min` needs parentheses
+    let operand = |f: &mut fmt::Formatter, i: usize, min: i32| {
+        let child = &children.unwrap()[i];
+        if priority(child) < min {
+            write!(f, "(?:")?;
+            write_ast(f, child)?;
+            write!(f, ")")
+        } else {
+            write_ast(f, child)
+        }
+    };
+    match ast.token() {
+        Symbol(_) | Class(_) => write!(f, "{}", ast.token()),
+        Epsilon => write!(f, "(?:)"),
+        EmptySet => write!(f, "\\0"),
+        // both are left associative, so a right operand of the same
+        // priority is parenthesized
+        &Operator(operator @ (Concat | Alter)) => {
+            operand(f, 0, operator.priority())?;
+            if operator == Alter {
+                write!(f, "|")?;
+            }
+            operand(f, 1, operator.priority() + 1)
+        }
+        &Operator(operator) => {
+            operand(f, 0, operator.priority())?;
+            write!(f, "{}", operator)
+        }
+        Capture(_, name) => {
+            match name {
+                Some(name) => write!(f, "(?<{}>", name)?,
+                None => write!(f, "(")?,
+            }
+            write_ast(f, &children.unwrap()[0])?;
+            write!(f, ")")
+        }
+    }
+}
+
+/// Write `c`, escaping it if it is one of `special`.
+fn write_symbol(f: &mut fmt::Formatter, c: char, special: &str) -> fmt::Result {
+    match c {
+        '\n' => write!(f, "\\n"),
+        '\t' => write!(f, "\\t"),
+        '\r' => write!(f, "\\r"),
+        _ if special.contains(c) => write!(f, "\\{}", c),
+        _ => write!(f, "{}", c),
+    }
+}
+
+impl Re {
+    /// The Thompson NFA of the pattern, along with the slots its capture
+    /// groups record into: entering the state of group `i` records slot
+    /// `2 * i` and leaving it records slot `2 * i + 1`.
+    pub fn thompson(&self) -> (Nfa<usize, char>, HashMap<usize, usize>) {
+        type Slots = HashMap<usize, usize>;
+        fn from(ast: &Ast<ReToken>, id: &mut usize, slots: &mut Slots) -> Nfa<usize, char> {
+            match ast.token() {
+                &Symbol(a) => {
+                    let result = Nfa::new(
+                        *id,
+                        hashset! {*id+1},
+                        hashmap! {
+                            (*id,Transition::Symbol(a)) => hashset! {*id+1}
+                        },
+                    );
+                    // Consume two state id
+                    *id += 2;
+                    result
+                }
+                Class(class) => {
+                    let mut transitions = HashMap::new();
+                    for (lo, hi) in class.ranges() {
+                        let input = if lo == hi {
+                            Transition::Symbol(lo)
+                        } else {
+                            Transition::Range(lo, hi)
+                        };
+                        transitions.insert((*id, input), hashset! {*id+1});
+                    }
+                    let result = Nfa::new(*id, hashset! {*id+1}, transitions);
+                    *id += 2;
+                    result
+                }
+                Epsilon => {
+                    let result = Nfa::new(*id, hashset! {*id}, HashMap::new());
+                    *id += 1;
+                    result
+                }
+                EmptySet => {
+                    let result = Nfa::new(*id, HashSet::new(), HashMap::new());
+                    *id += 1;
+                    result
+                }
+                Operator(Concat) => {
+                    let children = ast.children().unwrap();
+                    let (l, r) = (from(&children[0], id, slots), from(&children[1], id, slots));
+                    l.concat(r)
+                }
+
+                Operator(Alter) => {
+                    let children = ast.children().unwrap();
+                    let (l, r) = (from(&children[0], id, slots), from(&children[1], id, slots));
+                    let result = l.union(r, *id);
+                    *id += 1;
+                    result
+                }
+
+                Operator(Star) => {
+                    let children = ast.children().unwrap();
+                    let leaf = from(&children[0], id, slots);
+                    let result = leaf.star(*id, *id + 1);
+                    *id += 2;
+                    result
+                }
+
+                Operator(Plus) => {
+                    let children = ast.children().unwrap();
+                    let leaf = from(&children[0], id, slots);
+                    let result = leaf.plus(*id, *id + 1);
+                    *id += 2;
+                    result
+                }
+
+                Operator(Question) => {
+                    let children = ast.children().unwrap();
+                    let leaf = from(&children[0], id, slots);
+                    let result = leaf.optional(*id);
+                    *id += 1;
+                    result
+                }
+
+                &Operator(Repeat(min, max)) => {
+                    let children = ast.children().unwrap();
+                    let base = *id;
+                    let leaf = from(&children[0], id, slots);
+                    // Every piece is a copy of `leaf` moved to its own ids,
+                    // with the capture slots of its states
+                    let span = *id - base;
+                    let count = max.or(min.checked_add(1)).expect("repetition too large");
+                    *id = count
+                        .max(1)
+                        .checked_mul(span)
+                        .and_then(|size| base.checked_add(size))
+                        .expect("repetition too large");
+                    let inner = slots
+                        .iter()
+                        .filter(|&(&state, _)| state >= base)
+                        .map(|(&state, &slot)| (state, slot))
+                        .collect::<Vec<_>>();
+                    for k in 1..count {
+                        slots.extend(inner.iter().map(|&(state, slot)| (state + k * span, slot)));
+                    }
+                    let mut pieces = vec![];
+                    for k in 0..count {
+                        let piece = leaf.map_states(|&state| state + k * span);
+                        let piece = if k < min {
+                            piece
+                        } else if max.is_some() {
+                            let result = piece.optional(*id);
+                            *id += 1;
+                            result
+                        } else {
+                            let result = piece.star(*id, *id + 1);
+                            *id += 2;
+                            result
+                        };
+                        pieces.push(piece);
+                    }
+                    match pieces.into_iter().reduce(|acc, piece| acc.concat(piece)) {
+                        Some(result) => result,
+                        // `{0}` and `{0,0}` only match the empty string
+                        None => from(&Ast::new(Epsilon, None), id, slots),
+                    }
+                }
+
+                &Capture(index, _) => {
+                    let children = ast.children().unwrap();
+                    let leaf = from(&children[0], id, slots);
+                    let (enter, leave) = (*id, *id + 1);
+                    *id += 2;
+                    slots.insert(enter, 2 * index);
+                    slots.insert(leave, 2 * index + 1);
+                    let state = |state| Nfa::new(state, hashset! {state}, HashMap::new());
+                    state(enter).concat(leaf).concat(state(leave))
+                }
+                _ => unreachable!(),
+            }
+        }
+        let mut slots = HashMap::new();
+        let nfa = from(self.ast(), &mut 0, &mut slots);
+        (nfa, slots)
+    }
+}
+
+impl From<Re> for Nfa<usize, char> {
+    fn from(re: Re) -> Self {
+        re.thompson().0
+    }
+}
+
+impl Re {
+    /// Whether the pattern matches the empty string.
+    pub fn nullable(&self) -> bool {
+        nullable(self.ast())
+    }
+
+    /// The Brzozowski derivative by `c`: a pattern matching `w` exactly when
+    /// this one matches `c` followed by `w`. It is built with the simplifying
+    /// constructors below, from the pattern simplified and without its
+    /// capture groups.
+    pub fn derivative(&self, c: char) -> Re {
+        Re {
+            ast: Rc::unwrap_or_clone(derivative(&simplify(self.ast()), c)),
+        }
+    }
+
+    /// Match the whole of `input` by taking derivatives symbol by symbol,
+    /// without building an automaton.
+    pub fn is_match(&self, input: &[char]) -> bool {
+        let mut ast = Rc::new(self.ast.clone());
+        for &c in input {
+            if *ast.token() == EmptySet {
+                return false;
+            }
+            ast = derivative(&ast, c);
+        }
+        nullable(&ast)
+    }
+
+    /// A DFA whose states are the derivatives of the pattern, numbered in
+    /// the order they are found from the pattern itself at 0. Simplifying
+    /// alternations up to associativity, commutativity and idempotence
+    /// keeps them finite. The pattern itself is simplified first, so that
+    /// it is recognized when it comes back as a derivative. The empty
+    /// language is left out as the dead state.
+    pub fn to_dfa_by_derivatives(&self) -> Dfa<usize, char> {
+        let start = simplify(self.ast());
+        let mut ids = hashmap! {start.clone() => 0};
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        let mut accept_states = HashSet::new();
+        let mut transitions = HashMap::new();
+        while let Some(state) = queue.pop_front() {
+            let from = ids[&state];
+            if nullable(&state) {
+                accept_states.insert(from);
+            }
+            // symbols in the same block lie in the same leaves and so have the
+            // same derivative; those in no leaf lead to the empty language
+            let mut ranges = vec![];
+            leaf_ranges(&state, &mut ranges);
+            let mut edges = vec![];
+            for (lo, hi) in alphabet::partition(ranges) {
+                let next = derivative(&state, lo);
+                if *next.token() == EmptySet {
+                    continue;
+                }
+                let id = ids.len();
+                let to = *ids.entry(next.clone()).or_insert_with(|| {
+                    queue.push_back(next);
+                    id
+                });
+                dfa::push_edge(&mut edges, lo, hi, to);
+            }
+            for (lo, hi, to) in edges {
+                transitions.insert((from, dfa::Transition::range(lo, hi)), to);
+            }
+        }
+        Dfa::new(0, accept_states, transitions)
+    }
+}
+
+/// The number of nodes of `ast` once every counted repetition is replaced
+/// by its copies, as `Re::thompson` and `Re::glushkov` build them.
+fn unrolled_size(ast: &Ast<ReToken>) -> usize {
+    let children = ast.children().into_iter().flatten();
+    let size = children.fold(1, |size: usize, child| {
+        size.saturating_add(unrolled_size(child))
+    });
+    match ast.token() {
+        &Operator(Repeat(min, max)) => {
+            let count = max.unwrap_or_else(|| min.saturating_add(1)).max(1);
+            (size - 1).saturating_mul(count).saturating_add(1)
+        }
+        _ => size,
+    }
+}
+
+fn nullable(ast: &Ast<ReToken>) -> bool {
+    let children = ast.children();
+    let child = |i: usize| nullable(&children.unwrap()[i]);
+    match ast.token() {
+        Symbol(_) | Class(_) | EmptySet => false,
+        Epsilon => true,
+        Operator(Concat) => child(0) && child(1),
+        Operator(Alter) => child(0) || child(1),
+        Operator(Star) | Operator(Question) => true,
+        Operator(Plus) | Capture(..) => child(0),
+        &Operator(Repeat(min, _)) => min == 0 || child(0),
+        _ => unreachable!(),
+    }
+}
+
+fn derivative(ast: &Rc<Ast<ReToken>>, c: char) -> Rc<Ast<ReToken>> {
+    let children = ast.children();
+    let child = |i: usize| &children.unwrap()[i];
+    match ast.token() {
+        &Symbol(a) if a == c => leaf(Epsilon),
+        Class(class) if class.contains(c) => leaf(Epsilon),
+        Symbol(_) | Class(_) | Epsilon | EmptySet => leaf(EmptySet),
+        Operator(Concat) => {
+            let left = concat(derivative(child(0), c), child(1).clone());
+            if nullable(child(0)) {
+                alter(left, derivative(child(1), c))
+            } else {
+                left
+            }
+        }
+        Operator(Alter) => alter(derivative(child(0), c), derivative(child(1), c)),
+        Operator(Star) => concat(derivative(child(0), c), ast.clone()),
+        Operator(Plus) => concat(derivative(child(0), c), star(child(0).clone())),
+        Operator(Question) | Capture(..) => derivative(child(0), c),
+        // `r{m,n}` is `r r{m-1,n-1}`, and when `r` is nullable the
+        // derivative of the rest is already covered by that of the first `r`
+        &Operator(Repeat(_, Some(0))) => leaf(EmptySet),
+        &Operator(Repeat(min, max)) => {
+            let rest = repeat(
+                child(0).clone(),
+                min.saturating_sub(1),
+                max.map(|max| max - 1),
+            );
+            concat(derivative(child(0), c), rest)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Rebuild `ast` with the simplifying constructors, dropping its captures.
+fn simplify(ast: &Ast<ReToken>) -> Rc<Ast<ReToken>> {
+    let children = ast.children();
+    let child = |i: usize| simplify(&children.unwrap()[i]);
+    match ast.token() {
+        Operator(Concat) => concat(child(0), child(1)),
+        Operator(Alter) => alter(child(0), child(1)),
+        Operator(Star) => star(child(0)),
+        &Operator(Repeat(min, max)) => repeat(child(0), min, max),
+        &Operator(operator) => node(operator, vec![child(0)]),
+        Capture(..) => child(0),
+        _ => Rc::new(ast.clone()),
+    }
+}
+
+/// The symbol ranges of the leaves of `ast`, unmerged.
+fn leaf_ranges(ast: &Ast<ReToken>, result: &mut Vec<(char, char)>) {
+    match ast.token() {
+        &Symbol(c) => result.push((c, c)),
+        Class(class) => result.extend(class.ranges()),
+        _ => {}
+    }
+    for child in ast.children().into_iter().flatten() {
+        leaf_ranges(child, result);
+    }
+}
+
+fn leaf(token: ReToken) -> Rc<Ast<ReToken>> {
+    Rc::new(Ast::new(token, None))
+}
+
+fn node(operator: ReOperator, children: Vec<Rc<Ast<ReToken>>>) -> Rc<Ast<ReToken>> {
+    Rc::new(Ast::new(Operator(operator), Some(children)))
+}
+
+/// `left right`, nested to the left as the parser does, with `∅` absorbing
+/// and `()` the unit.
+fn concat(left: Rc<Ast<ReToken>>, right: Rc<Ast<ReToken>>) -> Rc<Ast<ReToken>> {
+    match (left.token(), right.token()) {
+        (EmptySet, _) | (_, Epsilon) => left,
+        (_, EmptySet) | (Epsilon, _) => right,
+        (_, Operator(Concat)) => {
+            let children = right.children().unwrap();
+            concat(concat(left, children[0].clone()), children[1].clone())
+        }
+        _ => node(Concat, vec![left, right]),
+    }
+}
+
+/// `left|right` with the alternatives flattened, sorted, deduplicated and
+/// nested to the left, and `∅` dropped from them.
+fn alter(left: Rc<Ast<ReToken>>, right: Rc<Ast<ReToken>>) -> Rc<Ast<ReToken>> {
+    fn flatten(ast: Rc<Ast<ReToken>>, result: &mut Vec<Rc<Ast<ReToken>>>) {
+        match ast.token() {
+            Operator(Alter) => {
+                for child in ast.children().unwrap() {
+                    flatten(child.clone(), result);
+                }
+            }
+            EmptySet => {}
+            _ => result.push(ast),
+        }
+    }
+    let mut alternatives = vec![];
+    flatten(left, &mut alternatives);
+    flatten(right, &mut alternatives);
+    alternatives.sort();
+    alternatives.dedup();
+    alternatives
+        .into_iter()
+        .reduce(|acc, alternative| node(Alter, vec![acc, alternative]))
+        .unwrap_or_else(|| leaf(EmptySet))
+}
+
+fn star(ast: Rc<Ast<ReToken>>) -> Rc<Ast<ReToken>> {
+    match ast.token() {
+        EmptySet | Epsilon => leaf(Epsilon),
+        Operator(Star) => ast,
+        _ => node(Star, vec![ast]),
+    }
+}
+
+fn repeat(ast: Rc<Ast<ReToken>>, min: usize, max: Option<usize>) -> Rc<Ast<ReToken>> {
+    match (min, max) {
+        (_, Some(0)) => leaf(Epsilon),
+        (0, None) => star(ast),
+        (1, Some(1)) => ast,
+        _ => match ast.token() {
+            Epsilon => ast,
+            EmptySet if min == 0 => leaf(Epsilon),
+            EmptySet => ast,
+            _ => node(Repeat(min, max), vec![ast]),
+        },
+    }
+}
+
+impl Re {
+    /// The Glushkov automaton of the pattern: an NFA without epsilon edges
+    /// whose states are the start state 0 and one state per position, see
+    /// `Positions`. Entering a state consumes the symbol of its position.
+    pub fn glushkov(&self) -> Nfa<usize, char> {
+        let positions = Positions::new(self);
+        let end = positions.end();
+        let mut accept_states = HashSet::new();
+        let mut transitions = HashMap::<_, HashSet<usize>>::new();
+        for (from, follow) in positions.follow.iter().enumerate().take(end) {
+            for &to in follow {
+                if to == end {
+                    accept_states.insert(from);
+                    continue;
+                }
+                for &(lo, hi) in &positions.labels[to] {
+                    let input = if lo == hi {
+                        Transition::Symbol(lo)
+                    } else {
+                        Transition::Range(lo, hi)
+                    };
+                    transitions.entry((from, input)).or_default().insert(to);
+                }
+            }
+        }
+        Nfa::new(0, accept_states, transitions)
+    }
+
+    /// A DFA built from the follow sets of the positions as by Aho, Sethi
+    /// and Ullman. Every state is the set of positions whose symbol may be
+    /// read next, starting from those that can start a match, and accepts
+    /// if it holds the end marker. States are numbered in the order they
+    /// are found from the start at 0.
+    pub fn to_dfa_by_positions(&self) -> Dfa<usize, char> {
+        let positions = Positions::new(self);
+        let start = positions.follow[0].clone();
+        let mut ids = hashmap! {start.clone() => 0};
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        let mut accept_states = HashSet::new();
+        let mut transitions = HashMap::new();
+        while let Some(state) = queue.pop_front() {
+            let from = ids[&state];
+            if state.contains(&positions.end()) {
+                accept_states.insert(from);
+            }
+            let ranges = state
+                .iter()
+                .flat_map(|&position| positions.labels[position].iter().cloned());
+            let mut edges = vec![];
+            for (lo, hi) in alphabet::partition(ranges) {
+                let mut next = BTreeSet::new();
+                for &position in &state {
+                    let labels = &positions.labels[position];
+                    if labels.iter().any(|&(a, b)| a <= lo && lo <= b) {
+                        next.extend(positions.follow[position].iter().cloned());
+                    }
+                }
+                // nothing can follow, so the DFA stays partial
+                if next.is_empty() {
+                    continue;
+                }
+                let id = ids.len();
+                let to = *ids.entry(next.clone()).or_insert_with(|| {
+                    queue.push_back(next);
+                    id
+                });
+                dfa::push_edge(&mut edges, lo, hi, to);
+            }
+            for (lo, hi, to) in edges {
+                transitions.insert((from, dfa::Transition::range(lo, hi)), to);
+            }
+        }
+        Dfa::new(0, accept_states, transitions)
+    }
+}
+
+/// The positions of a pattern, one per occurrence of a symbol or class once
+/// counted repetitions are unrolled, numbered from 1 in the order they
+/// appear. Position 0 stands before the first symbol and the last one is an
+/// end marker following the symbols that can end a match.
+struct Positions {
+    labels: Vec<Vec<(char, char)>>,
+    follow: Vec<BTreeSet<usize>>,
+}
+
+/// Whether a subpattern matches the empty string, and the positions it can
+/// start and end with.
+type Linear = (bool, BTreeSet<usize>, BTreeSet<usize>);
+
+impl Positions {
+    fn new(re: &Re) -> Self {
+        let mut positions = Positions {
+            labels: vec![vec![]],
+            follow: vec![BTreeSet::new()],
+        };
+        let start = (false, btreeset! {0}, btreeset! {0});
+        let pattern = positions.linearize(re.ast());
+        let pattern = positions.concat(start, pattern);
+        let end = positions.position(vec![]);
+        positions.concat(pattern, (false, btreeset! {end}, btreeset! {end}));
+        positions
+    }
+
+    fn end(&self) -> usize {
+        self.labels.len() - 1
+    }
+
+    fn position(&mut self, label: Vec<(char, char)>) -> usize {
+        self.labels.push(label);
+        self.follow.push(BTreeSet::new());
+        self.labels.len() - 1
+    }
+
+    fn concat(&mut self, left: Linear, right: Linear) -> Linear {
+        for &position in left.2.iter() {
+            self.follow[position].extend(right.1.iter().cloned());
+        }
+        let first = if left.0 { &left.1 | &right.1 } else { left.1 };
+        let last = if right.0 { &left.2 | &right.2 } else { right.2 };
+        (left.0 && right.0, first, last)
+    }
+
+    fn repeat(&mut self, linear: Linear) -> Linear {
+        for &position in linear.2.iter() {
+            self.follow[position].extend(linear.1.iter().cloned());
+        }
+        linear
+    }
+
+    fn linearize(&mut self, ast: &Ast<ReToken>) -> Linear {
+        let children = ast.children();
+        let child = |i: usize| &children.unwrap()[i];
+        match ast.token() {
+            &Symbol(c) => {
+                let position = self.position(vec![(c, c)]);
+                (false, btreeset! {position}, btreeset! {position})
+            }
+            Class(class) => {
+                let position = self.position(class.ranges());
+                (false, btreeset! {position}, btreeset! {position})
+            }
+            Epsilon => (true, BTreeSet::new(), BTreeSet::new()),
+            EmptySet => (false, BTreeSet::new(), BTreeSet::new()),
+            Operator(Concat) => {
+                let left = self.linearize(child(0));
+                let right = self.linearize(child(1));
+                self.concat(left, right)
+            }
+            Operator(Alter) => {
+                let (nullable, first, last) = self.linearize(child(0));
+                let right = self.linearize(child(1));
+                (nullable || right.0, &first | &right.1, &last | &right.2)
+            }
+            Operator(Star) => {
+                let linear = self.linearize(child(0));
+                optional(self.repeat(linear))
+            }
+            Operator(Plus) => {
+                let linear = self.linearize(child(0));
+                self.repeat(linear)
+            }
+            Operator(Question) => optional(self.linearize(child(0))),
+            // every repetition is a fresh copy of the subpattern, those past
+            // `min` being optional, or the last one looping if unbounded
+            &Operator(Repeat(min, max)) => {
+                let mut result = (true, BTreeSet::new(), BTreeSet::new());
+                let count = max.or(min.checked_add(1)).expect("repetition too large");
+                for k in 0..count {
+                    let piece = self.linearize(child(0));
+                    let piece = if k < min {
+                        piece
+                    } else if max.is_some() {
+                        optional(piece)
+                    } else {
+                        optional(self.repeat(piece))
+                    };
+                    result = self.concat(result, piece);
+                }
+                result
+            }
+            Capture(..) => self.linearize(child(0)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn optional((_, first, last): Linear) -> Linear {
+    (true, first, last)
+}
+
+impl<S: Hash + Eq + Ord + Clone> From<Dfa<S, char>> for Re {
+    fn from(dfa: Dfa<S, char>) -> Self {
+        let states = dfa
+            .transitions()
+            .iter()
+            .flat_map(|((from, _), to)| vec![from, to])
+            .chain(dfa.accept_states())
+            .chain(Some(dfa.start()))
+            .collect::<BTreeSet<_>>();
+        let ids = states
+            .iter()
+            .enumerate()
+            .map(|(id, &state)| (state, id))
+            .collect::<HashMap<_, _>>();
+        let edges = dfa
+            .transitions()
+            .iter()
+            .map(|((from, input), to)| (ids[from], Some((*input.start(), *input.end())), ids[to]));
+        let accept_states = dfa.accept_states().iter().map(|state| ids[state]);
+        eliminate(states.len(), ids[dfa.start()], accept_states, edges)
+    }
+}
+
+impl<S: Hash + Eq + Ord + Clone> From<Nfa<S, char>> for Re {
+    fn from(nfa: Nfa<S, char>) -> Self {
+        let states = nfa
+            .transitions()
+            .iter()
+            .flat_map(|((from, _), targets)| targets.iter().chain(Some(from)))
+            .chain(nfa.accept_states())
+            .chain(Some(nfa.start()))
+            .collect::<BTreeSet<_>>();
+        let ids = states
+            .iter()
+            .enumerate()
+            .map(|(id, &state)| (state, id))
+            .collect::<HashMap<_, _>>();
+        let ids = &ids;
+        let edges = nfa
+            .transitions()
+            .iter()
+            .flat_map(|((from, input), targets)| {
+                let (from, input) = (ids[from], input.range());
+                targets.iter().map(move |to| (from, input, ids[to]))
+            });
+        let accept_states = nfa.accept_states().iter().map(|state| ids[state]);
+        eliminate(states.len(), ids[nfa.start()], accept_states, edges)
+    }
+}
+
+/// Turn an automaton with the states `0..count` into a pattern by state
+/// elimination. Edges consume a range of symbols, or nothing if `None`.
+/// A fresh start and a fresh final state are added, and the others are
+/// removed one by one, replacing every path through the removed state with
+/// a direct edge. The state removed next is the one whose removal adds the
+/// least to the size of the labels.
+fn eliminate(
+    count: usize,
+    start: usize,
+    accept_states: impl IntoIterator<Item = usize>,
+    edges: impl IntoIterator<Item = (usize, Option<(char, char)>, usize)>,
+) -> Re {
+    // for every pair of states, whether an epsilon edge joins them and the
+    // symbols the other edges consume
+    let mut labels = BTreeMap::<(usize, usize), (bool, Vec<(char, char)>)>::new();
+    let (first, last) = (count, count + 1);
+    labels.entry((first, start)).or_default().0 = true;
+    for state in accept_states {
+        labels.entry((state, last)).or_default().0 = true;
+    }
+    for (from, input, to) in edges {
+        let label = labels.entry((from, to)).or_default();
+        match input {
+            Some(range) => label.1.push(range),
+            None => label.0 = true,
+        }
+    }
+    let mut edges = labels
+        .into_iter()
+        .map(|(pair, (epsilon, ranges))| {
+            let label = if epsilon {
+                leaf(Epsilon)
+            } else {
+                leaf(EmptySet)
+            };
+            (pair, alter(label, symbols(ranges)))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut states = (0..count).collect::<BTreeSet<_>>();
+    while !states.is_empty() {
+        let weight = |&state: &usize| {
+            let (mut ins, mut outs, mut around) = (vec![], vec![], 0);
+            for (&(from, to), label) in edges.iter() {
+                match (from == state, to == state) {
+                    (true, true) => around = size(label),
+                    (false, true) => ins.push(size(label)),
+                    (true, false) => outs.push(size(label)),
+                    _ => {}
+                }
+            }
+            let (paths_in, paths_out) = (ins.len() as i64, outs.len() as i64);
+            let ins = ins.iter().sum::<i64>() * (paths_out - 1);
+            let outs = outs.iter().sum::<i64>() * (paths_in - 1);
+            ins + outs + around * (paths_in * paths_out - 1)
+        };
+        let state = *states.iter().min_by_key(|state| weight(state)).unwrap();
+        states.remove(&state);
+
+        let around = edges.remove(&(state, state)).map(star);
+        let (mut ins, mut outs) = (vec![], vec![]);
+        for pair in edges.keys().cloned().collect::<Vec<_>>() {
+            if pair.1 == state {
+                ins.push((pair.0, edges.remove(&pair).unwrap()));
+            } else if pair.0 == state {
+                outs.push((pair.1, edges.remove(&pair).unwrap()));
+            }
+        }
+        for (from, label) in ins.iter() {
+            let label = match &around {
+                Some(around) => concat(label.clone(), around.clone()),
+                None => label.clone(),
+            };
+            for (to, out) in outs.iter() {
+                let path = concat(label.clone(), out.clone());
+                let edge = edges.entry((*from, *to)).or_insert_with(|| leaf(EmptySet));
+                *edge = alter(edge.clone(), path);
+            }
+        }
+    }
+    let ast = edges
+        .get(&(first, last))
+        .cloned()
+        .unwrap_or_else(|| leaf(EmptySet));
+    Re {
+        ast: Rc::unwrap_or_clone(tidy(&ast)),
+    }
+}
+
+/// A single leaf consuming the symbols in `ranges`, or `∅` for none. A
+/// class is negated if that takes fewer ranges, and lists both members of
+/// a range of two.
+fn symbols(ranges: Vec<(char, char)>) -> Rc<Ast<ReToken>> {
+    let ranges = alphabet::normalize(ranges);
+    let complement = alphabet::complement(ranges.iter().cloned());
+    let (negated, ranges) = match ranges[..] {
+        [] => return leaf(EmptySet),
+        [(lo, hi)] if lo == hi => return leaf(Symbol(lo)),
+        _ if complement.len() < ranges.len() => (true, complement),
+        _ => (false, ranges),
+    };
+    let items = ranges.into_iter().flat_map(|(lo, hi)| match lo.succ() {
+        Some(next) if next == hi => vec![(lo, lo), (hi, hi)],
+        _ => vec![(lo, hi)],
+    });
+    leaf(Class(CharClass::new(negated, items.collect())))
+}
+
+fn size(ast: &Ast<ReToken>) -> i64 {
+    1 + ast
+        .children()
+        .into_iter()
+        .flatten()
+        .map(|child| size(child))
+        .sum::<i64>()
+}
+
+/// Shorten what state elimination leaves behind: an alternative `()` turns
+/// the rest optional, `r* r` and `r r*` become `r+`, and `(?:r+)?` `r*`.
+fn tidy(ast: &Rc<Ast<ReToken>>) -> Rc<Ast<ReToken>> {
+    fn alternatives<'a>(ast: &'a Rc<Ast<ReToken>>, result: &mut Vec<&'a Rc<Ast<ReToken>>>) {
+        match ast.token() {
+            Operator(Alter) => {
+                for child in ast.children().unwrap() {
+                    alternatives(child, result);
+                }
+            }
+            _ => result.push(ast),
+        }
+    }
+    // the operand of `ast` if it applies `operator`
+    fn operand(ast: &Ast<ReToken>, operator: ReOperator) -> Option<&Rc<Ast<ReToken>>> {
+        Some(&ast.children()?[0]).filter(|_| *ast.token() == Operator(operator))
+    }
+    let children = ast.children();
+    let child = |i: usize| tidy(&children.unwrap()[i]);
+    match ast.token() {
+        Operator(Alter) => {
+            let mut result = vec![];
+            alternatives(ast, &mut result);
+            let empty = result.iter().any(|ast| *ast.token() == Epsilon);
+            let rest = result
+                .into_iter()
+                .filter(|ast| *ast.token() != Epsilon)
+                .map(tidy)
+                .reduce(|acc, alternative| node(Alter, vec![acc, alternative]));
+            match rest {
+                Some(rest) if !empty || nullable(&rest) => rest,
+                Some(rest) => match operand(&rest, Plus) {
+                    Some(repeated) => node(Star, vec![repeated.clone()]),
+                    None => node(Question, vec![rest]),
+                },
+                None => leaf(Epsilon),
+            }
+        }
+        Operator(Concat) => {
+            let (left, right) = (child(0), child(1));
+            // the last factor of `left` and the rest of it
+            let (init, last) = match left.children() {
+                Some(children) if *left.token() == Operator(Concat) => {
+                    (Some(&children[0]), &children[1])
+                }
+                _ => (None, &left),
+            };
+            let repeated = match (operand(last, Star), operand(&right, Star)) {
+                (Some(repeated), _) if *repeated == right => repeated,
+                (_, Some(repeated)) if repeated == last => repeated,
+                _ => return node(Concat, vec![left, right]),
+            };
+            let plus = node(Plus, vec![repeated.clone()]);
+            match init {
+                Some(init) => node(Concat, vec![init.clone(), plus]),
+                None => plus,
+            }
+        }
+        &Operator(operator) => node(operator, vec![child(0)]),
+        Capture(..) => Rc::new(Ast::new(ast.token().clone(), Some(vec![child(0)]))),
+        _ => ast.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::Dfa;
+    #[test]
+    fn test_re_parse() {
+        let re = Re::new("(1*2)|3");
+        let concat = Rc::new(Ast::new(
+            Operator(Concat),
+            Some(vec![
+                Rc::new(Ast::new(
+                    Operator(Star),
+                    Some(vec![Rc::new(Ast::new(Symbol('1'), None))]),
+                )),
+                Rc::new(Ast::new(Symbol('2'), None)),
+            ]),
+        ));
+        let alter = |left| {
+            let right = Rc::new(Ast::new(Symbol('3'), None));
+            Ast::new(Operator(Alter), Some(vec![left, right]))
+        };
+        let capture = Rc::new(Ast::new(Capture(1, None), Some(vec![concat.clone()])));
+        assert_eq!(
+            Re {
+                ast: alter(capture)
+            },
+            re
+        );
+        assert_eq!(alter(concat), Re::new("(?:1*2)|3").ast);
+    }
+
+    #[test]
+    fn test_re_parse_group() {
+        let leaf = |c| Rc::new(Ast::new(Symbol(c), None));
+        let capture = |index, name: Option<&str>, child| {
+            let token = Capture(index, name.map(String::from));
+            Rc::new(Ast::new(token, Some(vec![child])))
+        };
+        let concat = |l, r| Rc::new(Ast::new(Operator(Concat), Some(vec![l, r])));
+        let ast = concat(
+            capture(1, Some("year"), capture(2, None, leaf('a'))),
+            capture(3, Some("_x1"), leaf('b')),
+        );
+        assert_eq!(*ast, Re::new("(?<year>(a))(?:(?<_x1>b))").ast);
+
+        let error = |pattern| Re::parse(pattern).unwrap_err();
+        for pattern in &["a(?b)", "a(?<>b)", "a(?<1x>b)", "a(?<x", "a(?"] {
+            assert_eq!(ReError::new(ReErrorKind::InvalidGroup, 1), error(pattern));
+        }
+        assert_eq!(
+            ReError::new(ReErrorKind::DuplicateGroupName, 6),
+            error("(?<x>)(?<x>a)")
+        );
+        assert_eq!(ReError::new(ReErrorKind::UnmatchedLeft, 0), error("(?:a"));
+    }
+
+    #[test]
+    fn test_re_parse_error() {
+        use ReErrorKind::*;
+        let error = |pattern| Re::parse(pattern).unwrap_err();
+        assert_eq!(ReError::new(EmptyPattern, 0), error(""));
+        assert_eq!(ReError::new(UnmatchedLeft, 1), error("a(b|c"));
+        assert_eq!(ReError::new(UnmatchedLeft, 0), error("((a)"));
+        assert_eq!(ReError::new(UnmatchedRight, 3), error("a|b)"));
+        assert_eq!(ReError::new(DanglingOperator, 0), error("*a"));
+        assert_eq!(ReError::new(DanglingOperator, 3), error("a|(*)"));
+        assert_eq!(ReError::new(EmptyAlternative, 2), error("a|"));
+        assert_eq!(ReError::new(EmptyAlternative, 0), error("|a"));
+        assert_eq!(ReError::new(EmptyAlternative, 2), error("a||b"));
+        assert_eq!(ReError::new(EmptyAlternative, 1), error("(|a)"));
+        assert_eq!("unmatched `(` at offset 1", error("a(b").to_string());
+        assert!(Re::parse("(a|b)*c").is_ok());
+        assert!("a**".parse::<Re>().is_ok());
+    }
+
+    #[test]
+    fn test_re_parse_escape() {
+        let re = Re::new(r"\(\*\|\\\)\n\t");
+        let symbols = ['(', '*', '|', '\\', ')', '\n', '\t'];
+        let ast = symbols[1..]
+            .iter()
+            .fold(Ast::new(Symbol(symbols[0]), None), |acc, &c| {
+                Ast::new(
+                    Operator(Concat),
+                    Some(vec![Rc::new(acc), Rc::new(Ast::new(Symbol(c), None))]),
+                )
+            });
+        assert_eq!(Re { ast }, re);
+        assert!(Nfa::from(re).run(&symbols).accept());
+
+        let error = |pattern| Re::parse(pattern).unwrap_err();
+        assert_eq!(ReError::new(ReErrorKind::InvalidEscape, 1), error(r"a\"));
+        assert_eq!(ReError::new(ReErrorKind::InvalidEscape, 0), error(r"\q"));
+    }
+
+    #[test]
+    fn test_re_parse_class() {
+        let class = |negated, items| Ast::new(Class(CharClass::new(negated, items)), None);
+        assert_eq!(
+            class(false, vec![('a', 'z'), ('0', '9'), ('_', '_')]),
+            Re::new("[a-z0-9_]").ast
+        );
+        assert_eq!(
+            class(true, vec![('-', '-'), (']', ']'), ('a', 'a'), ('-', '-')]),
+            Re::new(r"[^-\]a-]").ast
+        );
+        assert_eq!(Ast::new(Class(CharClass::any()), None), Re::new(".").ast);
+
+        let error = |pattern| Re::parse(pattern).unwrap_err();
+        assert_eq!(
+            ReError::new(ReErrorKind::UnmatchedBracket, 1),
+            error("a[bc")
+        );
+        assert_eq!(ReError::new(ReErrorKind::EmptyClass, 0), error("[]"));
+        assert_eq!(ReError::new(ReErrorKind::InvalidRange, 3), error("[z-a]"));
+    }
+
+    #[test]
+    fn test_nfa_from_class() {
+        let nfa = Nfa::from(Re::new("[^a-y]"));
+        let transitions = hashmap! {
+            (0,Transition::Range('\0', '`')) => hashset!{1},
+            (0,Transition::Range('z', char::MAX)) => hashset!{1},
+        };
+        assert_eq!(Nfa::new(0, hashset! {1}, transitions), nfa);
+
+        let nfa = Nfa::from(Re::new("[a-z_][a-z0-9_]*|.x"));
+        let run = |input: &str| nfa.run(&input.chars().collect::<Vec<_>>()).accept();
+        assert!(run("snake_case0") && run("_") && run("!x") && run("\u{1F600}x"));
+        assert!(!run("0abc") && !run("a-b") && !run("-"));
+        let dfa = Dfa::from(nfa.clone());
+        for input in &["snake_case0", "_", "!x", "0abc", "a-b", "-"] {
+            let input = input.chars().collect::<Vec<_>>();
+            assert_eq!(nfa.run(&input).accept(), dfa.run(&input).accept());
+        }
+    }
+
+    #[test]
+    fn test_re_parse_repetition() {
+        let leaf = |c| Rc::new(Ast::new(Symbol(c), None));
+        let unary = |func, child| Rc::new(Ast::new(Operator(func), Some(vec![child])));
+        let concat = |l, r| Rc::new(Ast::new(Operator(Concat), Some(vec![l, r])));
+        let ast = concat(
+            concat(unary(Plus, leaf('a')), unary(Question, leaf('b'))),
+            unary(Repeat(2, None), unary(Repeat(1, Some(3)), leaf('c'))),
+        );
+        assert_eq!(*ast, Re::new("a+b?c{1,3}{2,}").ast);
+        assert_eq!(*unary(Repeat(3, Some(3)), leaf('d')), Re::new("d{3}").ast);
+        let ast = unary(Repeat(MAX_REPETITION, None), leaf('e'));
+        assert_eq!(*ast, Re::new("e{1000,}").ast);
+        // an overflowing bound is not taken for a missing one
+        let error = Re::parse("x|a{1,99999999999999999999999}").unwrap_err();
+        assert_eq!(ReError::new(ReErrorKind::InvalidRepetition, 3), error);
+        // the largest counts still build
+        let re = Re::new("a{1000}");
+        assert_eq!(hashset! {1000}, *re.glushkov().accept_states());
+        assert_eq!(hashset! {1999}, *Nfa::from(re).accept_states());
+        // counts multiply when repetitions nest or stack
+        let re = Re::new("(a{100}b){100}");
+        assert_eq!(hashset! {10100}, *re.glushkov().accept_states());
+        for (pattern, offset) in &[
+            ("((a{1000}){1000}){1000}", 10),
+            ("a{1000}{1000}{1000}", 7),
+            ("x(?:a{10}|b{20}){5000}", 16),
+            ("(a{1000}b)+{101}", 11),
+        ] {
+            let error = ReError::new(ReErrorKind::InvalidRepetition, *offset);
+            assert_eq!(Err(error), Re::parse(pattern).map(|_| ()));
+        }
+
+        let error = |pattern| Re::parse(pattern).unwrap_err();
+        assert_eq!(ReError::new(ReErrorKind::DanglingOperator, 0), error("+a"));
+        assert_eq!(ReError::new(ReErrorKind::DanglingOperator, 2), error("a|?"));
+        let huge = "a{18446744073709551615}";
+        let patterns = [
+            "a{",
+            "a{}",
+            "a{,2}",
+            "a{2,1}",
+            "a{1,x}",
+            "a{1001}",
+            "a{1,1001}",
+            huge,
+        ];
+        for pattern in &patterns {
+            assert_eq!(
+                ReError::new(ReErrorKind::InvalidRepetition, 1),
+                error(pattern)
+            );
+        }
+    }
+
+    #[test]
+    fn test_nfa_from_repetition() {
+        let accepts = |pattern, input: &str| {
+            let nfa = Nfa::from(Re::new(pattern));
+            nfa.run(&input.chars().collect::<Vec<_>>()).accept()
+        };
+        assert!(accepts("ab+", "abbb") && !accepts("ab+", "a"));
+        assert!(accepts("ab?c", "ac") && accepts("ab?c", "abc") && !accepts("ab?c", "abbc"));
+        assert!(accepts("(ab){2}", "abab") && !accepts("(ab){2}", "ab"));
+        assert!(!accepts("(ab){2}", "ababab"));
+        assert!(accepts("a{2,}", "aa") && accepts("a{2,}", "aaaaa") && !accepts("a{2,}", "a"));
+        assert!(accepts("a{0}b", "b") && !accepts("a{0}b", "ab"));
+        for n in 0..6 {
+            let input = "x".repeat(n);
+            assert_eq!((1..=3).contains(&n), accepts("x{1,3}", &input));
+            assert_eq!(n <= 2, accepts("(x|y){0,2}", &input));
+        }
+        assert!(accepts("(a|b){1,2}c", "bac") && accepts("(a|b){1,2}c", "ac"));
+    }
+
+    #[test]
+    fn test_re_parse_empty() {
+        let concat = |l, r| Ast::new(Operator(Concat), Some(vec![Rc::new(l), Rc::new(r)]));
+        assert_eq!(Ast::new(Epsilon, None), Re::new("(?:)").ast);
+        assert_eq!(
+            concat(Ast::new(Symbol('a'), None), Ast::new(Epsilon, None)),
+            Re::new("a(?:)").ast
+        );
+        let capture = Ast::new(
+            Capture(1, None),
+            Some(vec![Rc::new(Ast::new(Epsilon, None))]),
+        );
+        assert_eq!(capture, Re::new("()").ast);
+        assert_eq!(Ast::new(EmptySet, None), Re::new(r"\0").ast);
+        assert_eq!(
+            ReError::new(ReErrorKind::InvalidEscape, 1),
+            Re::parse(r"[\0]").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_nfa_from_empty() {
+        assert_eq!(
+            Nfa::new(0, hashset! {0}, HashMap::new()),
+            Nfa::from(Re::new("(?:)"))
+        );
+        let transitions = hashmap! {
+            (1, Transition::Epsilon) => hashset! {0},
+            (0, Transition::Epsilon) => hashset! {2},
+        };
+        assert_eq!(
+            (
+                Nfa::new(1, hashset! {2}, transitions),
+                hashmap! {1 => 2, 2 => 3}
+            ),
+            Re::new("()").thompson()
+        );
+        assert_eq!(
+            Nfa::new(0, HashSet::new(), HashMap::new()),
+            Nfa::from(Re::new(r"\0"))
+        );
+
+        let accepts = |pattern, input: &str| {
+            let input = input.chars().collect::<Vec<_>>();
+            let nfa = Nfa::from(Re::new(pattern));
+            let accept = nfa.run(&input).accept();
+            assert_eq!(accept, Dfa::from(nfa).run(&input).accept());
+            accept
+        };
+        assert!(accepts("()", "") && !accepts("()", "a"));
+        assert!(accepts("a(b|())", "a") && accepts("a(b|())", "ab"));
+        assert!(!accepts(r"\0", "") && accepts(r"\0*a", "a"));
+        assert!(accepts(r"\0*", "") && !accepts(r"a\0", "a"));
+        assert!(accepts(r"a|\0", "a"));
+    }
+
+    #[test]
+    fn test_dfa_from_re() {
+        use crate::dfa;
+        let dfa = Dfa::from(Nfa::from(Re::new("(a|b)*abb"))).minimize();
+        let minimal = Dfa::new(
+            0,
+            hashset! {3},
+            hashmap! {
+                (0,dfa::Transition::new('a')) => 1,
+                (0,dfa::Transition::new('b')) => 0,
+                (1,dfa::Transition::new('a')) => 1,
+                (1,dfa::Transition::new('b')) => 2,
+                (2,dfa::Transition::new('a')) => 1,
+                (2,dfa::Transition::new('b')) => 3,
+                (3,dfa::Transition::new('a')) => 1,
+                (3,dfa::Transition::new('b')) => 0,
+            },
+        );
+        assert_eq!(minimal, dfa);
+
+        let nfa = Nfa::from(Re::new("[a-z_][a-z0-9_]{0,15}|0x[0-9a-f]+|(ab|cd)*e?"));
+        let dfa = Dfa::from(nfa.clone());
+        let minimal = dfa.minimize();
+        for input in &[
+            "_",
+            "abc_123",
+            "0x1f",
+            "0x",
+            "abcdab",
+            "abcde",
+            "",
+            "ac",
+            "a2345678901234567",
+        ] {
+            let input = input.chars().collect::<Vec<_>>();
+            let accept = nfa.run(&input).accept();
+            assert_eq!(accept, dfa.run(&input).accept());
+            assert_eq!(accept, minimal.run(&input).accept());
+        }
+    }
+
+    #[test]
+    fn test_re_equivalent() {
+        let equivalent = |a, b| Re::new(a).equivalent(&Re::new(b));
+        assert_eq!(Ok(()), equivalent("(a|b)*", "(a*b*)*"));
+        assert_eq!(Ok(()), equivalent("(ab)*", "(ab|abab)*"));
+        assert_eq!(Ok(()), equivalent("a{2,3}", "aa|aaa"));
+        assert_eq!(Ok(()), equivalent("[a-c]x", "(a|b|c)x"));
+        assert_eq!(Ok(()), equivalent(r"a\0|()", "()"));
+        assert_eq!(Err("".to_string()), equivalent("a*", "a+"));
+        assert_eq!(Err("cx".to_string()), equivalent("[a-c]x", "(a|b)x"));
+        assert_eq!(Err("aaaa".to_string()), equivalent("a{2,}", "a{2,3}"));
+    }
+
+    #[test]
+    fn test_re_alphabet() {
+        let alphabet = |pattern| Re::new(pattern).alphabet();
+        assert_eq!(
+            Alphabet::new(vec![('a', 'c'), ('x', 'x')]),
+            alphabet("(a|b)*c{2}x?")
+        );
+        assert_eq!(
+            Alphabet::new(vec![('0', '9'), ('_', '_')]),
+            alphabet("[0-9]|_()")
+        );
+        assert_eq!(
+            Alphabet::new(vec![('\0', 'a'), ('c', char::MAX)]),
+            alphabet("[^b]")
+        );
+        assert!(alphabet(r"\0").is_empty());
+        let re = Re::new("[a-f]+x");
+        assert_eq!(re.alphabet(), Nfa::from(re.clone()).alphabet());
+    }
+
+    #[test]
+    fn test_nfa_from_re() {
+        use crate::re::Re;
+        let start = 2;
+        let accept_states = hashset! {7};
+        let transitions = hashmap! {
+            (0,Transition::Symbol('a')) => hashset!{1},
+            (4,Transition::Symbol('b')) => hashset!{5},
+            (6,Transition::Epsilon) => hashset!{4,7},
+            (1,Transition::Epsilon) => hashset!{2},
+            (5,Transition::Epsilon) => hashset!{6},
+            (2,Transition::Epsilon) => hashset!{0,3},
+            (3,Transition::Epsilon) => hashset!{6},
+        };
+        assert_eq!(
+            Nfa::new(start, accept_states, transitions),
+            Nfa::from(Re::new("a*b*"))
+        );
+    }
+
+    #[test]
+    fn test_re_derivative() {
+        let derivative = |pattern, c| Re::new(pattern).derivative(c);
+        assert_eq!(Re::new("b"), derivative("ab", 'a'));
+        assert_eq!(Re::new(r"\0"), derivative("a|b", 'c'));
+        // captures are dropped, also from the loop left to match
+        assert_eq!(Re::new("b(?:ab)*"), derivative("(ab)*", 'a'));
+        assert_eq!(Re::new("(?:a|b)*c"), derivative("(a|b)*c", 'b'));
+        assert_eq!(Re::new("a{1,2}"), derivative("a{2,3}", 'a'));
+        // alternatives are sorted and deduplicated
+        assert_eq!(Re::new("b|c"), derivative("ac|(ab|ac)|ab", 'a'));
+        assert_eq!(Re::new("[a-c]"), derivative("a[a-c]|b", 'a'));
+        assert!(derivative("[^x]", 'y').nullable());
+
+        assert!(Re::new("(a|())b?").nullable());
+        assert!(Re::new("(a*){3}").nullable());
+        assert!(!Re::new("a+|b{1,}").nullable());
+        assert!(!Re::new(r"\0*a").nullable());
+    }
+
+    #[test]
+    fn test_re_is_match() {
+        let patterns = [
+            "(a|b)*abb",
+            "a{2,3}b?",
+            "(?:x|yz)+",
+            "[^a]*a",
+            r"\0|()",
+            "(a?){2}",
+        ];
+        let inputs = [
+            "", "a", "abb", "babb", "aab", "aaab", "xyzx", "yy", "bba", "aa",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            let dfa = Dfa::from(Nfa::from(re.clone()));
+            for input in &inputs {
+                let input = input.chars().collect::<Vec<_>>();
+                assert_eq!(dfa.run(&input).accept(), re.is_match(&input));
+            }
+        }
+    }
+
+    #[test]
+    fn test_re_to_dfa_by_derivatives() {
+        let re = Re::new("(a|b)*abb");
+        let dfa = re.to_dfa_by_derivatives();
+        assert_eq!(4, dfa.minimize().transitions().len() / 2);
+        assert_eq!(Some(&0), dfa.next(&0, &'b'));
+        assert_eq!(None, dfa.next(&0, &'c'));
+
+        let patterns = [
+            "(a|b)*abb",
+            "[a-z_][a-z0-9_]*|[0-9]+",
+            "(ab|a)*(ba|b)*",
+            "(a{2,3}|b?){1,2}c",
+            "(?:.|\\n)*x.{3}",
+            r"\0",
+            "()",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            let dfa = re.to_dfa_by_derivatives();
+            assert_eq!(Ok(()), dfa.equivalent(&Dfa::from(Nfa::from(re.clone()))));
+            let thompson = Dfa::from(Nfa::from(re)).minimize();
+            let states = |dfa: &Dfa<usize, char>| {
+                let mut states = dfa.accept_states().clone();
+                states.insert(*dfa.start());
+                states.extend(dfa.transitions().keys().map(|(state, _)| *state));
+                states.len()
+            };
+            assert_eq!(states(&thompson), states(&dfa.minimize()));
+        }
+    }
+
+    #[test]
+    fn test_re_glushkov() {
+        let transitions = hashmap! {
+            (0, Transition::Symbol('a')) => hashset! {1, 3},
+            (1, Transition::Symbol('b')) => hashset! {2},
+            (3, Transition::Symbol('a')) => hashset! {3},
+        };
+        let expected = Nfa::new(0, hashset! {0, 2, 3}, transitions);
+        assert_eq!(expected, Re::new("ab|a*").glushkov());
+
+        let patterns = [
+            "(a|b)*abb",
+            "(a{2,3}|[b-d]?){1,2}c",
+            "(?:.|\\n)*x.{3}",
+            "(ab|a)+(ba|b)*",
+            r"a\0|()",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            let nfa = re.glushkov();
+            let transitions = nfa.transitions();
+            assert!(transitions
+                .keys()
+                .all(|(_, input)| *input != Transition::Epsilon));
+            assert_eq!(Ok(()), nfa.equivalent(&Nfa::from(re.clone())));
+        }
+        // one state per symbol once `{2,3}` is unrolled
+        let states = |nfa: Nfa<usize, char>| {
+            let transitions = nfa.transitions().iter();
+            let mut states = transitions
+                .flat_map(|((from, _), to)| to.iter().chain(Some(from)).cloned())
+                .collect::<HashSet<_>>();
+            states.extend(nfa.accept_states());
+            states.len()
+        };
+        assert_eq!(4, states(Re::new("a{2,3}").glushkov()));
+        assert_eq!(6, states(Re::new("(a|b)*abb").glushkov()));
+    }
+
+    #[test]
+    fn test_re_to_dfa_by_positions() {
+        let dfa = Re::new("(a|b)*abb").to_dfa_by_positions();
+        let states = dfa.transitions().keys().map(|(state, _)| state);
+        assert_eq!(4, states.collect::<HashSet<_>>().len());
+        assert_eq!(hashset! {3}, *dfa.accept_states());
+        assert_eq!(Some(&0), dfa.next(&0, &'b'));
+
+        let patterns = [
+            "(a{2,3}|[b-d]?){1,2}c",
+            "[a-z_][a-z0-9_]*|[0-9]+",
+            r"\0",
+            "()",
+            r"a\0|b",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            let dfa = re.to_dfa_by_positions();
+            assert_eq!(Ok(()), dfa.equivalent(&Dfa::from(Nfa::from(re))));
+        }
+
+        // no dead state for symbols that lead nowhere, as with derivatives
+        let states = |dfa: &Dfa<usize, char>| {
+            let mut states = hashset! {*dfa.start()};
+            states.extend(
+                dfa.transitions()
+                    .iter()
+                    .flat_map(|((from, _), to)| [*from, *to]),
+            );
+            states
+        };
+        let re = Re::new(r"a\0|b");
+        let dfa = re.to_dfa_by_positions();
+        assert_eq!(None, dfa.next(&0, &'a'));
+        assert_eq!(hashset! {0, 1}, states(&dfa));
+        assert_eq!(
+            states(&dfa).len(),
+            states(&re.to_dfa_by_derivatives()).len()
+        );
+    }
+
+    #[test]
+    fn test_re_display() {
+        let patterns = [
+            "(a|b)*abb",
+            "a(?:b|c)d|e",
+            "(?<key>[a-z_][a-z0-9_]*)=(?:[^;\\n]+)?;",
+            r"\(\)\[\]\{\}\*\+\?\|\.\\",
+            r"[\]\^\-\\a-c]x{2}y{1,}z{0,3}\0",
+            "(?:a|b)+?*.",
+            "\t\r\n(?:)",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            let text = re.to_string();
+            assert_eq!(re, Re::new(&text), "{:?} was written {:?}", pattern, text);
+        }
+        assert_eq!("(a|b)*abb", Re::new("(a|b)*abb").to_string());
+        assert_eq!("\\t\\n(?:)|\\0", Re::new("\t\n(?:)|\\0").to_string());
+        assert_eq!(".[^.]", Re::new(".[^.]").to_string());
+
+        // right operands of the same priority need parentheses
+        let leaf = |c| Rc::new(Ast::new(Symbol(c), None));
+        let node =
+            |operator, left, right| Rc::new(Ast::new(Operator(operator), Some(vec![left, right])));
+        let ast = node(Concat, leaf('a'), node(Concat, leaf('b'), leaf('c')));
+        let ast = node(Alter, ast, node(Alter, leaf('d'), leaf('e')));
+        let re = Re {
+            ast: Ast::new(Operator(Star), Some(vec![ast])),
+        };
+        assert_eq!("(?:a(?:bc)|(?:d|e))*", re.to_string());
+        assert_eq!(re, Re::new(&re.to_string()));
+    }
+
+    #[test]
+    fn test_re_display_round_trip() {
+        // patterns whose text comes back unchanged
+        let patterns = [
+            "((a)(?<b>b|(c)))*",
+            "(?<_0>(?:ab)+)?{2}",
+            "[a\\-][^\\]-\\^]",
+            "\\0*|(?:)+|.{0}",
+            "a|(?:b|c)",
+            "a(?:bc)",
+            "([^\\n]|\\\\\\.)*",
+            "é{1,}😀{3,4}",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            assert_eq!(*pattern, re.to_string());
+            assert_eq!(re, Re::new(&re.to_string()));
+        }
+        // others are written more plainly, with the same tree
+        let patterns = [
+            ("(a)|()", "(a)|((?:))"),
+            ("[-a-]", "[\\-a\\-]"),
+            ("((a))", "((a))"),
+        ];
+        for (pattern, text) in &patterns {
+            assert_eq!(*text, Re::new(pattern).to_string());
+            assert_eq!(Re::new(pattern), Re::new(text));
+        }
+        // trees the simplifying constructors build
+        for pattern in &["(a|b)*abb", "(ab|a)*(ba|b)*", "x?y?z"] {
+            let re = Re::new(pattern);
+            for c in "abxyz".chars() {
+                let derivative = re.derivative(c);
+                assert_eq!(derivative, Re::new(&derivative.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_re_from_automata() {
+        let minimal = |pattern| Dfa::from(Nfa::from(Re::new(pattern))).minimize();
+        assert_eq!("ab*", Re::from(minimal("ab*")).to_string());
+        assert_eq!("aaa?", Re::from(minimal("a{2,3}")).to_string());
+        assert_eq!(
+            "[0-9]+|[_a-z][0-9_a-z]*",
+            Re::from(minimal("[a-z_][a-z0-9_]*|[0-9]+")).to_string()
+        );
+        assert_eq!("(?:)", Re::from(minimal("(?:)|\\0")).to_string());
+        assert_eq!("\\0", Re::from(minimal("\\0a")).to_string());
+        assert_eq!("a*b*", Re::from(Nfa::from(Re::new("a*b*"))).to_string());
+
+        let patterns = [
+            "(a|b)*abb",
+            "(ab|a)*(ba|b)*",
+            "x?y?z",
+            "(?:.|\\n)*x.{2}",
+            "(a{2,3}|[b-d]?){1,2}c",
+        ];
+        for pattern in &patterns {
+            let re = Re::new(pattern);
+            assert_eq!(Ok(()), Re::from(minimal(pattern)).equivalent(&re));
+            assert_eq!(Ok(()), Re::from(Nfa::from(re.clone())).equivalent(&re));
+            assert_eq!(Ok(()), Re::from(re.glushkov()).equivalent(&re));
+        }
+
+        // every string over `ab` but `ab`
+        let ab = Dfa::from(Nfa::from(Re::new("ab")));
+        let other = ab.complement(&Alphabet::new(vec![('a', 'b')])).minimize();
+        let re = Re::from(other);
+        assert_eq!(
+            Ok(()),
+            re.equivalent(&Re::new("(?:)|[ab]|(?:aa|b[ab]|ab[ab])[ab]*"))
+        );
+    }
+}
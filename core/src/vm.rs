@@ -0,0 +1,743 @@
+use crate::alphabet::{self, Letter};
+use crate::automatan::Trace;
+use crate::dfa::Transition;
+use crate::dot;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// The runtime form of a DFA: states are `usize`s and transitions live in a
+/// flat table with one row per state and one column per symbol class. The
+/// row after the last state belongs to a dead state that every missing
+/// transition leads to.
+#[derive(Debug)]
+pub struct Vm<I: Hash + Eq> {
+    start: usize,
+    accept_states: HashSet<usize>,
+    transitions: HashMap<(usize, Transition<I>), usize>,
+    tags: HashMap<usize, usize>,
+    classes: Vec<(I, I)>,
+    table: Vec<usize>,
+    accepting: Vec<bool>,
+}
+
+impl<I: Letter> Vm<I> {
+    pub fn new(
+        start: usize,
+        accept_states: HashSet<usize>,
+        transitions: HashMap<(usize, Transition<I>), usize>,
+    ) -> Self {
+        let classes = alphabet::partition(
+            transitions
+                .keys()
+                .map(|(_, input)| (*input.start(), *input.end())),
+        );
+        let dead = transitions
+            .iter()
+            .flat_map(|((from, _), to)| [*from, *to])
+            .chain(accept_states.iter().cloned())
+            .fold(start, usize::max)
+            + 1;
+        let mut table = vec![dead; (dead + 1) * classes.len()];
+        for ((from, input), &to) in transitions.iter() {
+            let first = classes.partition_point(|class| class.0 < *input.start());
+            for class in first..classes.len() {
+                if classes[class].0 > *input.end() {
+                    break;
+                }
+                table[from * classes.len() + class] = to;
+            }
+        }
+        let mut accepting = vec![false; dead + 1];
+        for &state in accept_states.iter() {
+            accepting[state] = true;
+        }
+        Self {
+            start,
+            accept_states,
+            transitions,
+            tags: HashMap::new(),
+            classes,
+            table,
+            accepting,
+        }
+    }
+
+    /// The state reached from `state` by consuming `symbol`. Once the dead
+    /// state is reached, no input can lead to acceptance anymore.
+    pub fn step(&self, state: usize, symbol: &I) -> usize {
+        let class = self.classes.partition_point(|class| class.1 < *symbol);
+        match self.classes.get(class) {
+            Some(&(lo, _)) if lo <= *symbol => self.table[state * self.classes.len() + class],
+            _ => self.dead(),
+        }
+    }
+
+    /// The state reached from `state` by consuming `symbol`, if not dead.
+    pub fn next(&self, state: usize, symbol: &I) -> Option<usize> {
+        Some(self.step(state, symbol)).filter(|&next| !self.is_dead(next))
+    }
+
+    /// Whether the whole `input` is accepted.
+    pub fn is_match(&self, input: &[I]) -> bool {
+        let mut state = self.start;
+        for symbol in input {
+            state = self.step(state, symbol);
+            if self.is_dead(state) {
+                return false;
+            }
+        }
+        self.is_accept(state)
+    }
+
+    /// Like `Dfa::run`: the states visited on `input`, stopping early when
+    /// the dead state is reached.
+    pub fn run(&self, input: &[I]) -> Trace<usize> {
+        let mut state = self.start;
+        let mut trace = vec![];
+        for symbol in input {
+            trace.push(state);
+            state = self.step(state, symbol);
+            if self.is_dead(state) {
+                return Trace::new(false, trace);
+            }
+        }
+        trace.push(state);
+        Trace::new(self.is_accept(state), trace)
+    }
+}
+
+impl<I: Hash + Eq> Vm<I> {
+    /// Tag accept states with the index of the rule they accept for.
+    pub fn with_tags(mut self, tags: HashMap<usize, usize>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn accept_states(&self) -> &HashSet<usize> {
+        &self.accept_states
+    }
+
+    /// Rule indices of tagged accept states, see `Nfa::tags`.
+    pub fn tags(&self) -> &HashMap<usize, usize> {
+        &self.tags
+    }
+
+    /// The state without a way out, the last row of the table.
+    pub fn dead(&self) -> usize {
+        self.accepting.len() - 1
+    }
+
+    pub fn is_dead(&self, state: usize) -> bool {
+        state == self.dead()
+    }
+
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accepting.get(state).cloned().unwrap_or(false)
+    }
+
+    /// The symbol classes, i.e. the columns of the table.
+    pub fn classes(&self) -> &[(I, I)] {
+        &self.classes
+    }
+
+    /// The transition table, row after row, the dead state's row last.
+    pub fn table(&self) -> &[usize] {
+        &self.table
+    }
+}
+
+/// A `Vm<char>` lowered to static tables, which is what the `dfa!` macro
+/// expands to. The tables are laid out like those of `Vm`, and so are the
+/// dead state and `step`.
+#[derive(Clone, Copy, Debug)]
+pub struct ConstVm {
+    start: usize,
+    classes: &'static [(char, char)],
+    table: &'static [usize],
+    accepting: &'static [bool],
+}
+
+impl ConstVm {
+    pub const fn new(
+        start: usize,
+        classes: &'static [(char, char)],
+        table: &'static [usize],
+        accepting: &'static [bool],
+    ) -> Self {
+        Self {
+            start,
+            classes,
+            table,
+            accepting,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn dead(&self) -> usize {
+        self.accepting.len() - 1
+    }
+
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accepting.get(state).cloned().unwrap_or(false)
+    }
+
+    /// The state reached from `state` by consuming `c`, see `Vm::step`.
+    pub fn step(&self, state: usize, c: char) -> usize {
+        let class = self.classes.partition_point(|class| class.1 < c);
+        match self.classes.get(class) {
+            Some(&(lo, _)) if lo <= c => self.table[state * self.classes.len() + class],
+            _ => self.dead(),
+        }
+    }
+
+    /// Whether the whole `input` is accepted.
+    pub fn is_match(&self, input: &str) -> bool {
+        let mut state = self.start;
+        for c in input.chars() {
+            state = self.step(state, c);
+            if state == self.dead() {
+                return false;
+            }
+        }
+        self.is_accept(state)
+    }
+}
+
+impl<I: Hash + Eq + Debug> Vm<I> {
+    /// Render the state machine in Graphviz DOT, see `dot::render`.
+    pub fn to_dot(&self) -> String {
+        let edges = self
+            .transitions
+            .iter()
+            .map(|((from, input), to)| (*from, dot::range_label(input.start(), input.end()), *to))
+            .collect();
+        dot::render(&self.start, &self.accept_states, edges)
+    }
+}
+
+/// Decodes UTF-8 one byte at a time, feeding every code point to
+/// `rlu_step`. Malformed input moves the matcher to the dead state `-1`.
+const C_MATCHER: &str = "\
+struct rlu_matcher {
+	int state;
+	int need;
+	uint32_t c;
+	uint32_t min;
+};
+
+static void rlu_init(struct rlu_matcher *m) {
+	m->state = rlu_start;
+	m->need = 0;
+	m->c = 0;
+	m->min = 0;
+}
+
+static void rlu_feed(struct rlu_matcher *m, const char *s, size_t len) {
+	for (size_t i = 0; i < len && m->state >= 0; ++i) {
+		unsigned char b = (unsigned char)s[i];
+		if (m->need > 0 && (b & 0xC0) == 0x80) {
+			m->c = (m->c << 6) | (b & 0x3F);
+			m->need--;
+		} else if (m->need > 0) {
+			m->state = -1;
+		} else if (b < 0x80) {
+			m->c = b;
+			m->min = 0;
+		} else if ((b & 0xE0) == 0xC0) {
+			m->c = b & 0x1F;
+			m->need = 1;
+			m->min = 0x80;
+		} else if ((b & 0xF0) == 0xE0) {
+			m->c = b & 0x0F;
+			m->need = 2;
+			m->min = 0x800;
+		} else if ((b & 0xF8) == 0xF0) {
+			m->c = b & 0x07;
+			m->need = 3;
+			m->min = 0x10000;
+		} else {
+			m->state = -1;
+		}
+		if (m->state >= 0 && m->need == 0) {
+			int valid = m->c >= m->min && m->c <= 0x10FFFF && (m->c < 0xD800 || m->c > 0xDFFF);
+			m->state = valid ? rlu_step(m->state, m->c) : -1;
+		}
+	}
+}
+
+static int rlu_accepts(const struct rlu_matcher *m) {
+	return m->state >= 0 && m->need == 0 && rlu_accepting(m->state);
+}
+
+/* Whether the UTF-8 string `s` of `len` bytes is matched. */
+int match(const char *s, size_t len) {
+	struct rlu_matcher m;
+	rlu_init(&m);
+	rlu_feed(&m, s, len);
+	return rlu_accepts(&m);
+}
+";
+
+/// Reads the standard input a byte at a time and prints `accept` or
+/// `reject` for every line.
+const C_MAIN: &str = "\
+int main() {
+	struct rlu_matcher m;
+	int c, line = 0;
+	rlu_init(&m);
+	while ((c = getchar()) != EOF) {
+		if (c == '\\n') {
+			puts(rlu_accepts(&m) ? \"accept\" : \"reject\");
+			rlu_init(&m);
+			line = 0;
+		} else {
+			char b = (char)c;
+			rlu_feed(&m, &b, 1);
+			line = 1;
+		}
+	}
+	if (line)
+		puts(rlu_accepts(&m) ? \"accept\" : \"reject\");
+	return 0;
+}
+";
+
+/// A C expression for the code point `c`: a character literal when it is
+/// printable ASCII or has a short escape, a hexadecimal number otherwise.
+fn c_literal(c: char) -> String {
+    match c {
+        '\'' => r"'\''".to_string(),
+        '\\' => r"'\\'".to_string(),
+        '\n' => r"'\n'".to_string(),
+        '\t' => r"'\t'".to_string(),
+        '\r' => r"'\r'".to_string(),
+        ' '..='~' => format!("'{}'", c),
+        _ => format!("0x{:X}", c as u32),
+    }
+}
+
+impl Vm<char> {
+    /// The edges leaving each state, states and edges in ascending order.
+    fn jmp_table(&self) -> BTreeMap<usize, Vec<(Transition<char>, usize)>> {
+        let mut map: BTreeMap<usize, Vec<(Transition<char>, usize)>> = BTreeMap::new();
+        for rule in self.transitions.iter() {
+            let (left, target) = rule;
+            let (state, input) = left;
+            map.entry(*state).or_default().push((*input, *target));
+        }
+        for branchs in map.values_mut() {
+            branchs.sort_by_key(|(input, _)| *input.start());
+        }
+        map
+    }
+
+    fn switch_statement(&self) -> String {
+        let jmp_table = self.jmp_table();
+        jmp_table
+            .into_iter()
+            .map(|(header, branchs)| {
+                let header = format!("\tcase {}:\n", header);
+                let branchs = branchs
+                    .into_iter()
+                    .map(|(input, target)| {
+                        let (lo, hi) = (*input.start(), *input.end());
+                        let condition = match (lo, hi) {
+                            _ if lo == hi => format!("c == {}", c_literal(lo)),
+                            ('\0', char::MAX) => "1".to_string(),
+                            ('\0', _) => format!("c <= {}", c_literal(hi)),
+                            (_, char::MAX) => format!("c >= {}", c_literal(lo)),
+                            _ => format!("c >= {} && c <= {}", c_literal(lo), c_literal(hi)),
+                        };
+                        format!("\t\tif ({})\n\t\t\treturn {};\n", condition, target)
+                    })
+                    .fold(String::new(), |acc, ref string| acc + string);
+                header + &branchs + "\t\treturn -1;\n"
+            })
+            .fold(String::new(), |acc, ref string| acc + string)
+    }
+
+    fn accept_statement(&self) -> String {
+        let mut accept_states = self.accept_states.iter().collect::<Vec<_>>();
+        accept_states.sort();
+        let condition = accept_states
+            .into_iter()
+            .map(|state| format!("state == {}", state))
+            .fold(String::new(), |acc, sub| {
+                if acc.is_empty() {
+                    sub
+                } else {
+                    acc + " || " + &sub
+                }
+            });
+        if condition.is_empty() {
+            "0".to_string()
+        } else {
+            condition
+        }
+    }
+
+    /// C source defining `int match(const char *s, size_t len)`, which
+    /// tells whether a UTF-8 string is accepted. Everything else it defines
+    /// is `static` and prefixed with `rlu_`.
+    pub fn compile(&self) -> String {
+        format!(
+            "\
+#include <stddef.h>
+#include <stdint.h>
+
+static const int rlu_start = {};
+
+static int rlu_step(int state, uint32_t c) {{
+\tswitch (state) {{
+{}\tdefault:
+\t\treturn -1;
+\t}}
+}}
+
+static int rlu_accepting(int state) {{
+\treturn {};
+}}
+
+{}",
+            self.start,
+            self.switch_statement(),
+            self.accept_statement(),
+            C_MATCHER,
+        )
+    }
+
+    /// A complete C program around `Vm::compile`, matching every line of
+    /// its standard input.
+    pub fn compile_program(&self) -> String {
+        format!("#include <stdio.h>\n{}\n{}", self.compile(), C_MAIN)
+    }
+}
+
+/// How `Vm::compile_rust_with` lays out the state machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RustStyle {
+    /// One `match` arm per transition.
+    Match,
+    /// A static table indexed by state and symbol class.
+    Table,
+}
+
+impl Vm<char> {
+    /// Rust source defining `pub fn is_match(input: &str) -> bool`, which
+    /// needs nothing but `core`. Small machines become a `match`, larger
+    /// ones a transition table. A build script can write it to a file in
+    /// `OUT_DIR`, to be pulled into the crate with `include!`.
+    pub fn compile_rust(&self) -> String {
+        let style = if self.transitions.len() > 64 {
+            RustStyle::Table
+        } else {
+            RustStyle::Match
+        };
+        self.compile_rust_with(style)
+    }
+
+    pub fn compile_rust_with(&self, style: RustStyle) -> String {
+        let body = match style {
+            RustStyle::Match => self.rust_match(),
+            RustStyle::Table => self.rust_table(),
+        };
+        format!("pub fn is_match(input: &str) -> bool {{\n{}}}\n", body)
+    }
+
+    fn rust_match(&self) -> String {
+        let mut arms = String::new();
+        for (state, branchs) in self.jmp_table() {
+            for (input, target) in branchs {
+                let pattern = if input.start() == input.end() {
+                    format!("{:?}", input.start())
+                } else {
+                    format!("{:?}..={:?}", input.start(), input.end())
+                };
+                arms += &format!("            ({}, {}) => {},\n", state, pattern, target);
+            }
+        }
+        if arms.is_empty() && self.accept_states.contains(&self.start) {
+            return "    input.is_empty()\n".to_string();
+        } else if arms.is_empty() || self.accept_states.is_empty() {
+            // nothing is ever read from `state` without an accept state
+            return "    let _ = input;\n    false\n".to_string();
+        }
+        let mut accept_states = self.accept_states.iter().collect::<Vec<_>>();
+        accept_states.sort();
+        let accept_states = accept_states
+            .into_iter()
+            .map(|state| state.to_string())
+            .collect::<Vec<_>>();
+        let accept = format!("matches!(state, {})", accept_states.join(" | "));
+        format!(
+            "    let mut state = {};
+    for c in input.chars() {{
+        state = match (state, c) {{
+{}            _ => return false,
+        }};
+    }}
+    {}
+",
+            self.start, arms, accept
+        )
+    }
+
+    fn rust_table(&self) -> String {
+        let states = self.dead();
+        let width = if states < 1 << 8 {
+            "u8"
+        } else if states < 1 << 16 {
+            "u16"
+        } else {
+            "u32"
+        };
+        let classes = self
+            .classes
+            .iter()
+            .map(|(lo, hi)| format!("        ({:?}, {:?}),\n", lo, hi))
+            .collect::<String>();
+        let mut table = String::new();
+        for row in self.table.chunks(self.classes.len().max(1)).take(states) {
+            let row = row.iter().map(|to| to.to_string()).collect::<Vec<_>>();
+            table += &format!("        [{}],\n", row.join(", "));
+        }
+        if self.classes.is_empty() {
+            table = "        [],\n".repeat(states);
+        }
+        let accepting = self.accepting[..states]
+            .iter()
+            .map(|accept| format!("        {},\n", accept))
+            .collect::<String>();
+        format!(
+            "    static CLASSES: [(char, char); {classes}] = [
+{}    ];
+    static TABLE: [[{width}; {classes}]; {states}] = [
+{}    ];
+    static ACCEPT: [bool; {states}] = [
+{}    ];
+    let mut state = {};
+    for c in input.chars() {{
+        let class = CLASSES.partition_point(|&(_, hi)| hi < c);
+        match CLASSES.get(class) {{
+            Some(&(lo, _)) if lo <= c => state = TABLE[state][class] as usize,
+            _ => return false,
+        }}
+        if state == {states} {{
+            return false;
+        }}
+    }}
+    ACCEPT[state]
+",
+            classes,
+            table,
+            accepting,
+            self.start,
+            classes = self.classes.len(),
+            width = width,
+            states = states,
+        )
+    }
+}
+
+#[test]
+fn it_works() {
+    use crate::dfa::Dfa;
+    use maplit::{hashmap, hashset};
+    let start = 'a';
+    let accept_states = hashset! {'c'};
+    let transitions = hashmap! {
+        ('a',Transition::new('a')) => 'b',
+        ('a',Transition::new('b')) => 'c',
+        ('b',Transition::new('b')) => 'c',
+        ('c',Transition::new('c')) => 'b',
+        ('c',Transition::new('a')) => 'b',
+    };
+    let dfa = Dfa::new(start, accept_states, transitions);
+    let vm = Vm::from(dfa);
+    let dot = vm.to_dot();
+    assert!(dot.contains("\tstart -> 0;\n"));
+    assert!(dot.contains("\t0 [label=\"0\", shape=circle];\n"));
+    assert!(dot.contains("\t2 [label=\"2\", shape=doublecircle];\n"));
+    assert!(dot.contains("\t0 -> 1 [label=\"'a'\"];\n"));
+    assert!(dot.contains("\t2 -> 1 [label=\"'a', 'c'\"];\n"));
+    let code = vm.compile();
+    assert!(code.contains("static const int rlu_start = 0;\n"));
+    assert!(code.contains("\tcase 1:\n\t\tif (c == 'b')\n\t\t\treturn 2;\n\t\treturn -1;\n"));
+    assert!(code.contains("\treturn state == 2;\n"));
+}
+
+#[test]
+fn test_vm_run() {
+    use crate::dfa::Dfa;
+    use crate::nfa::Nfa;
+    use crate::re::Re;
+    use maplit::{hashmap, hashset};
+    let vm = Vm::new(
+        0,
+        hashset! {1},
+        hashmap! {
+            (0, Transition::range(b'a', b'z')) => 1,
+            (1, Transition::range(b'a', b'z')) => 1,
+            (1, Transition::range(b'0', b'9')) => 1,
+        },
+    );
+    assert_eq!(2, vm.dead());
+    assert!(vm.is_match(b"x86") && !vm.is_match(b"8x") && !vm.is_match(b""));
+    assert_eq!(vec![0, 1, 1], *vm.run(b"ab").trace());
+    assert!(!vm.run(b"a-b").accept());
+    assert_eq!(vec![0, 1], *vm.run(b"a-b").trace());
+
+    // input can be fed a chunk at a time
+    let mut state = vm.start();
+    for chunk in [&b"ab"[..], b"c1", b"2"] {
+        state = chunk
+            .iter()
+            .fold(state, |state, symbol| vm.step(state, symbol));
+    }
+    assert!(vm.is_accept(state));
+    assert!(vm.is_dead(vm.step(state, &b'-')));
+    assert!(vm.is_dead(vm.step(vm.dead(), &b'a')));
+
+    let dfa = Dfa::from(Nfa::from(Re::new("[a-f]+x|0x[0-9a-f]+|(ab|cd)*e?")));
+    let vm = Vm::from(dfa.minimize());
+    for input in &["abx", "0x1f", "0x", "abcdab", "abcde", "", "ac", "fx"] {
+        let input = input.chars().collect::<Vec<_>>();
+        assert_eq!(dfa.run(&input).accept(), vm.is_match(&input));
+        assert_eq!(dfa.run(&input).accept(), vm.run(&input).accept());
+    }
+}
+
+#[test]
+fn test_vm_compile() {
+    use maplit::{hashmap, hashset};
+    assert_eq!(r"'\''", c_literal('\''));
+    assert_eq!(r"'\\'", c_literal('\\'));
+    assert_eq!(r"'\n'", c_literal('\n'));
+    assert_eq!("'~'", c_literal('~'));
+    assert_eq!("0x7F", c_literal('\x7f'));
+    assert_eq!("0x1F600", c_literal('😀'));
+
+    let vm = Vm::new(
+        0,
+        hashset! {1},
+        hashmap! {
+            (0, Transition::new('"')) => 1,
+            (1, Transition::range('\0', '\'')) => 1,
+        },
+    );
+    let code = vm.compile();
+    assert!(code.contains("int match(const char *s, size_t len) {"));
+    assert!(code.contains("\t\tif (c == '\"')\n\t\t\treturn 1;\n"));
+    assert!(code.contains("\t\tif (c <= '\\'')\n\t\t\treturn 1;\n"));
+    assert!(code.contains("\treturn state == 1;\n"));
+    assert!(!code.contains("int main()"));
+    assert!(vm.compile_program().contains("int main()"));
+
+    let empty = Vm::<char>::new(0, hashset! {}, hashmap! {});
+    assert!(empty.compile().contains("\treturn 0;\n"));
+}
+
+#[test]
+fn test_vm_compile_stable() {
+    use crate::dfa::Dfa;
+    use crate::nfa::Nfa;
+    use crate::re::Re;
+    let compile = || Vm::from(Dfa::from(Nfa::from(Re::new("b|a[0-9]*"))).minimize()).compile();
+    let code = compile();
+    let step = "\
+\tswitch (state) {
+\tcase 0:
+\t\tif (c == 'a')
+\t\t\treturn 1;
+\t\tif (c == 'b')
+\t\t\treturn 2;
+\t\treturn -1;
+\tcase 1:
+\t\tif (c >= '0' && c <= '9')
+\t\t\treturn 1;
+\t\treturn -1;
+\tdefault:
+";
+    assert!(code.contains(step));
+    assert!(code.contains("\treturn state == 1 || state == 2;\n"));
+    for _ in 0..8 {
+        assert_eq!(code, compile());
+    }
+}
+
+#[test]
+fn test_vm_compile_rust() {
+    use crate::dfa::Dfa;
+    use crate::nfa::Nfa;
+    use crate::re::Re;
+    use maplit::{hashmap, hashset};
+    let vm = |pattern| Vm::from(Dfa::from(Nfa::from(Re::new(pattern))).minimize());
+    let code = "\
+pub fn is_match(input: &str) -> bool {
+    let mut state = 0;
+    for c in input.chars() {
+        state = match (state, c) {
+            (0, '\\'') => 1,
+            (0, 'b') => 2,
+            (1, '0'..='9') => 1,
+            _ => return false,
+        };
+    }
+    matches!(state, 1 | 2)
+}
+";
+    assert_eq!(code, vm("b|'[0-9]*").compile_rust());
+
+    let code = vm("b|'[0-9]*").compile_rust_with(RustStyle::Table);
+    assert!(code.contains("    static CLASSES: [(char, char); 3] = [\n        ('\\'', '\\''),\n"));
+    assert!(code.contains("    static TABLE: [[u8; 3]; 3] = [\n        [1, 3, 2],\n"));
+    assert!(code.contains("        if state == 3 {\n"));
+
+    assert!(vm("a{70}")
+        .compile_rust()
+        .contains("static TABLE: [[u8; 1]; 71]"));
+    assert_eq!(
+        "pub fn is_match(input: &str) -> bool {\n    input.is_empty()\n}\n",
+        vm("()").compile_rust()
+    );
+    // transitions that never reach an accept state
+    let never = Vm::new(0, hashset! {}, hashmap! {(0, Transition::new('a')) => 1});
+    assert_eq!(
+        "pub fn is_match(input: &str) -> bool {\n    let _ = input;\n    false\n}\n",
+        never.compile_rust()
+    );
+}
+
+#[test]
+fn test_const_vm() {
+    use crate::dfa::Dfa;
+    use crate::nfa::Nfa;
+    use crate::re::Re;
+    // the tables `dfa!` would embed
+    let vm = Vm::from(Dfa::from(Nfa::from(Re::new(r"(a|b)*abb|\\\n😀{2,}"))).minimize());
+    let accepting = (0..=vm.dead()).map(|state| vm.is_accept(state));
+    let abb = ConstVm::new(
+        vm.start(),
+        Box::leak(vm.classes().to_vec().into_boxed_slice()),
+        Box::leak(vm.table().to_vec().into_boxed_slice()),
+        Box::leak(accepting.collect::<Vec<_>>().into_boxed_slice()),
+    );
+    for input in &["abb", "babaabb", "abba", "", "abc", "\\\n😀😀", "\\\n😀"] {
+        let chars = input.chars().collect::<Vec<_>>();
+        assert_eq!(vm.is_match(&chars), abb.is_match(input));
+    }
+    let state = "abb"
+        .chars()
+        .fold(abb.start(), |state, c| abb.step(state, c));
+    assert!(abb.is_accept(state));
+    assert_eq!(abb.dead(), abb.step(abb.start(), 'c'));
+    assert_eq!(abb.dead(), abb.step(abb.dead(), 'a'));
+}
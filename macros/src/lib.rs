@@ -0,0 +1,81 @@
+//! Procedural macros re-exported by `regular-language-utils`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+use regular_language_utils_core::dfa::Dfa;
+use regular_language_utils_core::nfa::Nfa;
+use regular_language_utils_core::re::Re;
+use regular_language_utils_core::vm::Vm;
+
+/// The span of the character at `offset` in the value of `literal`, or of
+/// the closing quote at its end. Falls back to the whole literal when its
+/// source has escapes, or the compiler cannot point inside literals.
+fn offset_span(literal: &LitStr, offset: usize) -> Span {
+    let token = literal.token();
+    let source = token.to_string();
+    let value = literal.value();
+    let (open, close) = match (source.find('"'), source.rfind('"')) {
+        (Some(open), Some(close)) if open < close => (open + 1, close),
+        _ => return literal.span(),
+    };
+    if source[open..close] != value {
+        return literal.span();
+    }
+    let start = open
+        + value
+            .char_indices()
+            .nth(offset)
+            .map_or(value.len(), |(at, _)| at);
+    let len = source[start..].chars().next().map_or(1, char::len_utf8);
+    token
+        .subspan(start..start + len)
+        .unwrap_or_else(|| literal.span())
+}
+
+/// Parse, determinize and minimize a pattern at compile time, expanding to
+/// a constant `regular_language_utils::vm::ConstVm`. A malformed pattern is
+/// a compile error pointing at the offending character of the literal.
+///
+/// ```
+/// use regular_language_utils::dfa;
+/// use regular_language_utils::vm::ConstVm;
+///
+/// const ABB: ConstVm = dfa!("(a|b)*abb");
+/// assert!(ABB.is_match("babb") && !ABB.is_match("abba"));
+/// ```
+///
+/// ```compile_fail
+/// let vm = regular_language_utils::dfa!("(a|b*abb");
+/// ```
+#[proc_macro]
+pub fn dfa(input: TokenStream) -> TokenStream {
+    let pattern = parse_macro_input!(input as LitStr);
+    let re = match Re::parse(&pattern.value()) {
+        Ok(re) => re,
+        Err(err) => {
+            let message = format!("invalid pattern: {}", err);
+            return syn::Error::new(offset_span(&pattern, err.offset()), message)
+                .to_compile_error()
+                .into();
+        }
+    };
+    let vm = Vm::from(Dfa::from(Nfa::from(re)).minimize());
+    let start = vm.start();
+    let classes = vm.classes().iter().map(|(lo, hi)| quote!((#lo, #hi)));
+    let table = vm.table();
+    let accepting = (0..=vm.dead()).map(|state| vm.is_accept(state));
+    let expanded = quote! {
+        ::regular_language_utils::vm::ConstVm::new(
+            #start,
+            &[#(#classes),*],
+            &[#(#table),*],
+            &[#(#accepting),*],
+        )
+    };
+    expanded.into()
+}
@@ -0,0 +1,197 @@
+use regular_language_utils::dot;
+use regular_language_utils::nfa::Transition;
+use regular_language_utils::{Dfa, Nfa, Re, Vm};
+use std::env;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::process;
+
+const USAGE: &str = "\
+usage: rlu <command> <pattern> [<argument>]
+
+commands:
+    parse <pattern> [tree|sexpr]
+                              print the syntax tree of the pattern, or
+                              the tree as an S-expression on one line
+    nfa <pattern>             print the Thompson NFA
+    dfa <pattern>             print the DFA from the subset construction
+    minimize <pattern>        print the minimal DFA
+    dot <pattern> [nfa|dfa]   print an automaton in Graphviz DOT, the
+                              minimal DFA by default
+    compile <pattern> [c|rust]
+                              print a C program matching each line of
+                              its input against the pattern, or a Rust
+                              function `is_match`
+    match <pattern> <input>   print whether the minimal DFA accepts the
+                              input and the states it went through
+    equiv <pattern> <pattern> print whether the patterns are equivalent,
+                              or a shortest string matched by only one
+
+Automata are printed as a `start` line, an `accept` line and one
+`<from> <symbol> <to>` line per transition. `match` and `equiv` exit
+with 1 when the answer is no, and every command exits with 2 on bad
+usage or a malformed pattern.";
+
+/// What to print and the exit code.
+struct Output {
+    text: String,
+    code: i32,
+}
+
+impl Output {
+    fn new(text: String, code: i32) -> Self {
+        Self { text, code }
+    }
+}
+
+fn parse(pattern: &str) -> Result<Re, String> {
+    Re::parse(pattern).map_err(|err| {
+        let caret = " ".repeat(err.offset());
+        format!("error: {}\n    {}\n    {}^", err, pattern, caret)
+    })
+}
+
+fn describe<S: Ord + Display>(
+    start: &S,
+    mut accept: Vec<&S>,
+    mut edges: Vec<(&S, String, &S)>,
+) -> String {
+    accept.sort();
+    edges.sort();
+    let accept = accept
+        .iter()
+        .map(|state| state.to_string())
+        .collect::<Vec<_>>();
+    let mut text = format!("start {}\naccept {}\n", start, accept.join(" "));
+    for (from, label, to) in edges {
+        text += &format!("{} {} {}\n", from, label, to);
+    }
+    text
+}
+
+fn describe_nfa(nfa: &Nfa<usize, char>) -> String {
+    let mut edges = vec![];
+    for ((from, input), targets) in nfa.transitions() {
+        let label = match input {
+            Transition::Epsilon => "ε".to_string(),
+            Transition::Symbol(c) => format!("{:?}", c),
+            Transition::Range(lo, hi) => dot::range_label(lo, hi),
+        };
+        edges.extend(targets.iter().map(|to| (from, label.clone(), to)));
+    }
+    describe(nfa.start(), nfa.accept_states().iter().collect(), edges)
+}
+
+fn describe_dfa<S: Hash + Eq + Ord + Display>(dfa: &Dfa<S, char>) -> String {
+    let edges = dfa
+        .transitions()
+        .iter()
+        .map(|((from, input), to)| (from, dot::range_label(input.start(), input.end()), to))
+        .collect();
+    describe(dfa.start(), dfa.accept_states().iter().collect(), edges)
+}
+
+fn run(args: &[String]) -> Result<Output, String> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let nfa = |pattern| parse(pattern).map(Nfa::from);
+    let minimal = |pattern| nfa(pattern).map(|nfa| Dfa::from(nfa).minimize());
+    let text = match args[..] {
+        ["parse", pattern] | ["parse", pattern, "tree"] => parse(pattern)?.ast().to_tree(),
+        ["parse", pattern, "sexpr"] => format!("{}\n", parse(pattern)?.ast().to_sexpr()),
+        ["nfa", pattern] => describe_nfa(&nfa(pattern)?),
+        ["dfa", pattern] => describe_dfa(&Dfa::from(nfa(pattern)?)),
+        ["minimize", pattern] => describe_dfa(&minimal(pattern)?),
+        ["dot", pattern] => minimal(pattern)?.to_dot(),
+        ["dot", pattern, "nfa"] => nfa(pattern)?.to_dot(),
+        ["dot", pattern, "dfa"] => Dfa::from(nfa(pattern)?).to_dot(),
+        ["compile", pattern] | ["compile", pattern, "c"] => {
+            Vm::from(minimal(pattern)?).compile_program()
+        }
+        ["compile", pattern, "rust"] => Vm::from(minimal(pattern)?).compile_rust(),
+        ["match", pattern, input] => {
+            let trace = minimal(pattern)?.run(&input.chars().collect::<Vec<_>>());
+            let states = trace
+                .trace()
+                .iter()
+                .map(|state| state.to_string())
+                .collect::<Vec<_>>();
+            let verdict = if trace.accept() { "accept" } else { "reject" };
+            let text = format!("{}\n{}\n", verdict, states.join(" "));
+            return Ok(Output::new(text, if trace.accept() { 0 } else { 1 }));
+        }
+        ["equiv", a, b] => {
+            return Ok(match parse(a)?.equivalent(&parse(b)?) {
+                Ok(()) => Output::new("equivalent\n".to_string(), 0),
+                Err(input) => Output::new(format!("different {:?}\n", input), 1),
+            });
+        }
+        ["help"] | ["-h"] | ["--help"] => format!("{}\n", USAGE),
+        _ => return Err(USAGE.to_string()),
+    };
+    Ok(Output::new(text, 0))
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(output) => {
+            print!("{}", output.text);
+            process::exit(output.code);
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn run(args: &[&str]) -> Result<(String, i32), String> {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        super::run(&args).map(|output| (output.text, output.code))
+    }
+
+    #[test]
+    fn test_cli() {
+        let text = "start 0\naccept 1\n0 'a' 1\n1 'a' 1\n".to_string();
+        assert_eq!(Ok((text, 0)), run(&["minimize", "aa*"]));
+        let text = "start 0\naccept 3\n0 'b' 1\n1 ε 2\n2 'a' 3\n".to_string();
+        assert_eq!(Ok((text, 0)), run(&["nfa", "ba"]));
+        let text = "start {0}\naccept {1}\n{0} 'a'-'c' {1}\n".to_string();
+        assert_eq!(Ok((text, 0)), run(&["dfa", "[a-c]"]));
+
+        assert_eq!(
+            Ok(("accept\n0 1 1\n".to_string(), 0)),
+            run(&["match", "ab*", "ab"])
+        );
+        assert_eq!(
+            Ok(("reject\n0\n".to_string(), 1)),
+            run(&["match", "ab*", "b"])
+        );
+        assert_eq!(
+            Ok(("equivalent\n".to_string(), 0)),
+            run(&["equiv", "a|b", "[ab]"])
+        );
+        let text = "different \"aa\"\n".to_string();
+        assert_eq!(Ok((text, 1)), run(&["equiv", "a*", "a?"]));
+
+        assert!(run(&["dot", "a", "nfa"])
+            .unwrap()
+            .0
+            .starts_with("digraph {"));
+        assert!(run(&["compile", "a"]).unwrap().0.contains("int main()"));
+        assert!(run(&["compile", "a", "rust"])
+            .unwrap()
+            .0
+            .starts_with("pub fn is_match(input: &str) -> bool {"));
+        let text = "concat\n├── a\n└── *\n    └── b\n".to_string();
+        assert_eq!(Ok((text, 0)), run(&["parse", "ab*"]));
+        let text = "(concat a (* b))\n".to_string();
+        assert_eq!(Ok((text, 0)), run(&["parse", "ab*", "sexpr"]));
+        let error = "error: unmatched `)` at offset 2\n    ab)\n      ^".to_string();
+        assert_eq!(Err(error), run(&["parse", "ab)"]));
+        assert_eq!(Err(USAGE.to_string()), run(&["match", "a"]));
+    }
+}
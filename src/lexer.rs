@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::automatan::Match;
+use crate::dfa::Dfa;
+use crate::nfa::{Nfa, Transition};
+use crate::re::Re;
+use crate::vm::Vm;
+
+/// Collects the token rules of a lexer, earlier rules taking priority.
+pub struct LexerBuilder<T> {
+    rules: Vec<(T, Re)>,
+}
+
+impl<T> Default for LexerBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> LexerBuilder<T> {
+    pub fn new() -> Self {
+        Self { rules: vec![] }
+    }
+
+    /// Add a rule producing `token` for the strings matched by `re`.
+    pub fn rule(mut self, token: T, re: Re) -> Self {
+        self.rules.push((token, re));
+        self
+    }
+
+    /// Union the rules into one NFA whose accept states are tagged with the
+    /// index of their rule, and turn it into a minimal DFA. A state
+    /// accepting for several rules keeps the tag of the first one.
+    pub fn build(self) -> Lexer<T> {
+        let start = (self.rules.len(), 0);
+        let mut starts = HashSet::new();
+        let mut accept_states = HashSet::new();
+        let mut tags = HashMap::new();
+        let mut transitions = HashMap::new();
+        let mut tokens = vec![];
+        for (rule, (token, re)) in self.rules.into_iter().enumerate() {
+            let nfa = Nfa::from(re).map_states(|&state| (rule, state));
+            starts.insert(*nfa.start());
+            tags.extend(nfa.accept_states().iter().map(|&state| (state, rule)));
+            accept_states.extend(nfa.accept_states().iter().cloned());
+            transitions.extend(nfa.transitions().clone());
+            tokens.push(token);
+        }
+        transitions.insert((start, Transition::Epsilon), starts);
+        let nfa = Nfa::new(start, accept_states, transitions).with_tags(tags);
+        Lexer {
+            tokens,
+            vm: Vm::from(Dfa::from(nfa).minimize()),
+        }
+    }
+}
+
+/// A tokenizer built by `LexerBuilder`.
+pub struct Lexer<T> {
+    tokens: Vec<T>,
+    vm: Vm<char>,
+}
+
+impl<T> Lexer<T> {
+    pub fn vm(&self) -> &Vm<char> {
+        &self.vm
+    }
+
+    /// The longest non-empty token at the start of `input`, with the byte
+    /// length of its lexeme.
+    pub fn longest_token(&self, input: &str) -> Option<(&T, usize)> {
+        let mut state = self.vm.start();
+        let mut longest = None;
+        for (at, c) in input.char_indices() {
+            match self.vm.next(state, &c) {
+                Some(next) => state = next,
+                None => break,
+            }
+            if let Some(&rule) = self.vm.tags().get(&state) {
+                longest = Some((&self.tokens[rule], at + c.len_utf8()));
+            }
+        }
+        longest
+    }
+
+    /// Split `input` into tokens, always taking the longest one and, among
+    /// rules matching the same length, the one added first. Each run of
+    /// input where no token starts becomes an error. Spans are byte offsets,
+    /// so `&input[span.range()]` is the lexeme.
+    pub fn tokenize<'a>(&'a self, input: &'a str) -> Tokens<'a, T> {
+        Tokens {
+            lexer: self,
+            input,
+            at: 0,
+        }
+    }
+}
+
+/// The tokens of an input, see `Lexer::tokenize`.
+pub struct Tokens<'a, T> {
+    lexer: &'a Lexer<T>,
+    input: &'a str,
+    at: usize,
+}
+
+impl<'a, T> Iterator for Tokens<'a, T> {
+    type Item = Result<(&'a T, Match), Match>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.at;
+        if let Some((token, len)) = self.lexer.longest_token(&self.input[start..]) {
+            self.at += len;
+            return Some(Ok((token, Match::new(start, self.at))));
+        }
+        let mut chars = self.input[start..].chars();
+        while let Some(c) = chars.next() {
+            self.at += c.len_utf8();
+            if self.lexer.longest_token(chars.as_str()).is_some() {
+                break;
+            }
+        }
+        Some(Err(Match::new(start, self.at))).filter(|_| start < self.at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashset;
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Token {
+        If,
+        Ident,
+        Number,
+        Op,
+        Space,
+    }
+
+    #[test]
+    fn test_lexer() {
+        use Token::*;
+        let lexer = LexerBuilder::new()
+            .rule(If, Re::new("if"))
+            .rule(Ident, Re::new("[a-z_][a-z0-9_]*"))
+            .rule(Number, Re::new("[0-9]+(\\.[0-9]+)?"))
+            .rule(Op, Re::new("[-+*/=<>]|==|<=|>="))
+            .rule(Space, Re::new("[ \\t\\n]+"))
+            .build();
+        let input = "if iffy<=3.5 ?? x==é1";
+        let tokens = lexer
+            .tokenize(input)
+            .map(|token| match token {
+                Ok((&token, span)) => (Some(token), &input[span.range()]),
+                Err(span) => (None, &input[span.range()]),
+            })
+            .collect::<Vec<_>>();
+        let expected = vec![
+            (Some(If), "if"),
+            (Some(Space), " "),
+            (Some(Ident), "iffy"),
+            (Some(Op), "<="),
+            (Some(Number), "3.5"),
+            (Some(Space), " "),
+            (None, "??"),
+            (Some(Space), " "),
+            (Some(Ident), "x"),
+            (Some(Op), "=="),
+            (None, "é"),
+            (Some(Number), "1"),
+        ];
+        assert_eq!(expected, tokens);
+
+        assert_eq!(Some((&Ident, 2)), lexer.longest_token("ab+"));
+        assert_eq!(None, lexer.longest_token(""));
+        assert_eq!(0, lexer.tokenize("").count());
+    }
+
+    #[test]
+    fn test_lexer_tags() {
+        let lexer = LexerBuilder::new()
+            .rule("ab", Re::new("ab"))
+            .rule("a+b", Re::new("a+b"))
+            .rule("word", Re::new("[ab]+"))
+            .build();
+        assert_eq!(Some((&"ab", 2)), lexer.longest_token("ab"));
+        assert_eq!(Some((&"a+b", 3)), lexer.longest_token("aab"));
+        assert_eq!(Some((&"word", 4)), lexer.longest_token("aaba"));
+        // "ab" and "aab" accept for different rules and stay apart
+        let tags = lexer.vm().tags().values().cloned().collect::<HashSet<_>>();
+        assert_eq!(hashset! {0, 1, 2}, tags);
+    }
+}
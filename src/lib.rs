@@ -1,12 +1,12 @@
-pub mod ast;
-pub mod automatan;
-pub mod dfa;
-pub mod nfa;
-pub mod re;
-pub mod utils;
-pub mod vm;
+pub mod lexer;
+pub mod pike;
+
+pub use regular_language_utils_core::{alphabet, ast, automatan, class, dfa, dot, nfa, re, vm};
 
 pub use dfa::Dfa;
+pub use lexer::LexerBuilder;
 pub use nfa::Nfa;
+pub use pike::Pike;
 pub use re::Re;
+pub use regular_language_utils_macros::dfa;
 pub use vm::Vm;
@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::Ast;
+use crate::automatan::Match;
+use crate::nfa::{Nfa, Transition};
+use crate::re::{Re, ReToken};
+
+/// The spans of one match: group 0 is the whole match and group `i` the
+/// `i`-th capture group of the pattern, `None` if it took no part in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Captures {
+    groups: Vec<Option<Match>>,
+    names: HashMap<String, usize>,
+}
+
+impl Captures {
+    pub fn new(groups: Vec<Option<Match>>, names: HashMap<String, usize>) -> Self {
+        Self { groups, names }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Match> {
+        self.groups.get(index).cloned().flatten()
+    }
+
+    pub fn name(&self, name: &str) -> Option<Match> {
+        self.names.get(name).and_then(|&index| self.get(index))
+    }
+
+    pub fn groups(&self) -> &[Option<Match>] {
+        &self.groups
+    }
+}
+
+/// Threads of the simulation in priority order, at most one per state.
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    seen: HashSet<usize>,
+}
+
+impl Threads {
+    fn new() -> Self {
+        Self {
+            list: vec![],
+            seen: HashSet::new(),
+        }
+    }
+}
+
+/// Matcher for patterns with capture groups. It simulates the Thompson NFA
+/// of the pattern like `Nfa::find`, but every thread carries the offsets it
+/// recorded in the capture slots. Threads are kept in priority order, so
+/// that of several ways to match, the one taking the leftmost alternative
+/// and repeating as often as possible early on decides the groups.
+#[derive(Clone, Debug)]
+pub struct Pike {
+    nfa: Nfa<usize, char>,
+    slots: HashMap<usize, usize>,
+    names: HashMap<String, usize>,
+    groups: usize,
+    // epsilon edges by priority, which follows the Thompson numbering
+    epsilon: HashMap<usize, Vec<usize>>,
+    steps: HashMap<usize, Vec<(Transition<char>, usize)>>,
+}
+
+impl Pike {
+    pub fn new(re: &Re) -> Self {
+        fn groups(ast: &Ast<ReToken>, names: &mut HashMap<String, usize>, count: &mut usize) {
+            if let ReToken::Capture(index, name) = ast.token() {
+                *count = (*count).max(*index);
+                if let Some(name) = name {
+                    names.insert(name.clone(), *index);
+                }
+            }
+            for child in ast.children().into_iter().flatten() {
+                groups(child, names, count);
+            }
+        }
+        let (mut names, mut count) = (HashMap::new(), 0);
+        groups(re.ast(), &mut names, &mut count);
+
+        let (nfa, slots) = re.thompson();
+        let mut epsilon = HashMap::<usize, Vec<usize>>::new();
+        let mut steps = HashMap::<usize, Vec<(Transition<char>, usize)>>::new();
+        for (&(state, transition), targets) in nfa.transitions() {
+            if transition == Transition::Epsilon {
+                epsilon.entry(state).or_default().extend(targets);
+            } else {
+                let targets = targets.iter().map(|&target| (transition, target));
+                steps.entry(state).or_default().extend(targets);
+            }
+        }
+        for targets in epsilon.values_mut() {
+            targets.sort();
+        }
+        Self {
+            nfa,
+            slots,
+            names,
+            groups: count,
+            epsilon,
+            steps,
+        }
+    }
+
+    /// The number of capture groups, not counting the whole match.
+    pub fn group_count(&self) -> usize {
+        self.groups
+    }
+
+    /// Add a thread entering `state` at offset `at`, and the threads its
+    /// epsilon edges lead to, unless a thread of higher priority got there
+    /// first.
+    fn add(&self, threads: &mut Threads, state: usize, slots: Vec<Option<usize>>, at: usize) {
+        let mut stack = vec![(state, slots)];
+        while let Some((state, mut slots)) = stack.pop() {
+            if !threads.seen.insert(state) {
+                continue;
+            }
+            if let Some(&slot) = self.slots.get(&state) {
+                slots[slot] = Some(at);
+            }
+            for &next in self.epsilon.get(&state).into_iter().flatten().rev() {
+                stack.push((next, slots.clone()));
+            }
+            threads.list.push((state, slots));
+        }
+    }
+
+    /// The groups of the leftmost-longest match in `input`.
+    pub fn captures(&self, input: &[char]) -> Option<Captures> {
+        self.captures_at(input, 0)
+    }
+
+    /// The groups of the leftmost-longest match starting at or after `from`,
+    /// whose span is the one `Nfa::find_at` returns.
+    pub fn captures_at(&self, input: &[char], from: usize) -> Option<Captures> {
+        let mut threads = Threads::new();
+        let mut best: Option<Vec<Option<usize>>> = None;
+        for at in from..=input.len() {
+            // threads started earlier come first, a new one goes last
+            if best.is_none() {
+                let mut slots = vec![None; 2 * (self.groups + 1)];
+                slots[0] = Some(at);
+                self.add(&mut threads, *self.nfa.start(), slots, at);
+            }
+            let accepted = threads
+                .list
+                .iter()
+                .find(|(state, _)| self.nfa.accept_states().contains(state));
+            if let Some((_, slots)) = accepted {
+                let mut slots = slots.clone();
+                slots[1] = Some(at);
+                best = Some(slots);
+            }
+            if let Some(best) = &best {
+                threads.list.retain(|(_, slots)| slots[0] <= best[0]);
+            }
+            if at == input.len() || (best.is_some() && threads.list.is_empty()) {
+                break;
+            }
+
+            let mut next = Threads::new();
+            for (state, slots) in threads.list {
+                for &(transition, target) in self.steps.get(&state).into_iter().flatten() {
+                    if transition.matches(&input[at]) {
+                        self.add(&mut next, target, slots.clone(), at + 1);
+                    }
+                }
+            }
+            threads = next;
+        }
+        let groups = best?
+            .chunks(2)
+            .map(|span| match *span {
+                [Some(start), Some(end)] => Some(Match::new(start, end)),
+                _ => None,
+            })
+            .collect();
+        Some(Captures::new(groups, self.names.clone()))
+    }
+}
+
+impl From<Re> for Pike {
+    fn from(re: Re) -> Self {
+        Pike::new(&re)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn captures(pattern: &str, input: &str) -> Option<Vec<Option<(usize, usize)>>> {
+        let input = input.chars().collect::<Vec<_>>();
+        let groups = Pike::from(Re::new(pattern)).captures(&input)?;
+        let spans = groups.groups().iter();
+        Some(
+            spans
+                .map(|span| span.map(|span| (span.start(), span.end())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_pike_captures() {
+        let spans = vec![Some((1, 6)), Some((1, 3)), Some((4, 6))];
+        assert_eq!(Some(spans), captures("([a-z]+)-([0-9]+)", "-ab-12-"));
+        assert_eq!(None, captures("(a)(b)", "ba"));
+
+        // the last iteration of a loop is the one captured
+        let spans = vec![Some((0, 3)), Some((2, 3))];
+        assert_eq!(Some(spans), captures("(a|b)*", "abb"));
+        let spans = vec![Some((0, 1)), None, Some((0, 1))];
+        assert_eq!(Some(spans), captures("(a)|(b)", "b"));
+        let spans = vec![Some((0, 0)), None];
+        assert_eq!(Some(spans), captures("(a)*", "b"));
+        let spans = vec![Some((0, 2)), Some((0, 1)), Some((1, 1))];
+        assert_eq!(Some(spans), captures("(a?)(a?)b", "ab"));
+        // captures inside counted repetitions and greedy loops
+        let spans = vec![Some((0, 4)), Some((3, 4))];
+        assert_eq!(Some(spans), captures("([ab]){2,4}", "abab"));
+        let spans = vec![Some((0, 4)), Some((0, 3)), Some((3, 3))];
+        assert_eq!(Some(spans), captures("(a*)(a*)b", "aaab"));
+        // the whole match is the leftmost-longest one
+        let spans = vec![Some((0, 4)), Some((2, 4))];
+        assert_eq!(Some(spans), captures("ab(cd)|c", "abcd"));
+    }
+
+    #[test]
+    fn test_pike_named() {
+        let pike = Pike::from(Re::new(r"(?<key>[a-z]+)=(?<value>[0-9]*)(?:;|())"));
+        assert_eq!(3, pike.group_count());
+        let input = "  width=640;".chars().collect::<Vec<_>>();
+        let captures = pike.captures(&input).unwrap();
+        assert_eq!(Some(Match::new(2, 7)), captures.name("key"));
+        assert_eq!(Some(Match::new(8, 11)), captures.name("value"));
+        assert_eq!(Some(Match::new(2, 12)), captures.get(0));
+        assert_eq!(None, captures.get(3));
+        assert_eq!(None, captures.name("width"));
+
+        let nfa = Nfa::from(Re::new(r"(?<key>[a-z]+)=(?<value>[0-9]*)(?:;|())"));
+        for input in &["a=1;b=", "==x=;", "", "q"] {
+            let input = input.chars().collect::<Vec<_>>();
+            for from in 0..=input.len() {
+                let found = pike.captures_at(&input, from).and_then(|c| c.get(0));
+                assert_eq!(nfa.find_at(&input, from), found);
+            }
+        }
+    }
+}